@@ -0,0 +1,294 @@
+//! Compares the dispatch loops of the stack and register machines on a few representative workloads.
+//!
+//! Run with `cargo bench`. An optional argument filters workloads by name.
+
+use bytecode_vm_tests::{
+    packed::Packed,
+    program::{self, Stmt},
+    register, threaded, two_byte, variable_length, BinOp, Machine, Value,
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+type Workload = fn() -> Vec<Stmt>;
+type RegisterWorkload = fn() -> register::bytecode::Pool;
+
+const ITERATIONS: i64 = 100_000;
+const RUNS: usize = 5;
+
+/// Runs `body` `ITERATIONS` times. The body must leave the stack as it found it.
+fn counted(body: Vec<Stmt>) -> Vec<Stmt> {
+    vec![
+        Stmt::Literal(Value::Int(ITERATIONS)),
+        Stmt::While(
+            vec![
+                Stmt::Literal(Value::Int(1)),
+                Stmt::BinOp(BinOp::Sub),
+                Stmt::Dup,
+            ],
+            body,
+        ),
+    ]
+}
+
+fn arithmetic() -> Vec<Stmt> {
+    counted(vec![
+        Stmt::Dup,
+        Stmt::Literal(Value::Int(3)),
+        Stmt::BinOp(BinOp::Mul),
+        Stmt::Literal(Value::Int(7)),
+        Stmt::BinOp(BinOp::Add),
+        Stmt::Literal(Value::Float(0.5)),
+        Stmt::BinOp(BinOp::Mul),
+        Stmt::Literal(Value::Int(11)),
+        Stmt::BinOp(BinOp::Mod),
+        Stmt::If(vec![]),
+    ])
+}
+
+fn strings() -> Vec<Stmt> {
+    counted(vec![
+        Stmt::Literal("Hello, ".into()),
+        Stmt::Literal("World!".into()),
+        Stmt::BinOp(BinOp::Add),
+        Stmt::Literal(Value::Int(4)),
+        Stmt::BinOp(BinOp::Mul),
+        Stmt::Literal("".into()),
+        Stmt::BinOp(BinOp::Ne),
+        Stmt::If(vec![]),
+    ])
+}
+
+fn branches() -> Vec<Stmt> {
+    counted(vec![
+        Stmt::Dup,
+        Stmt::Literal(Value::Int(3)),
+        Stmt::BinOp(BinOp::Mod),
+        Stmt::IfElse(
+            vec![
+                Stmt::Dup,
+                Stmt::Literal(Value::Int(2)),
+                Stmt::BinOp(BinOp::LT),
+                Stmt::IfElse(vec![Stmt::Nop], vec![Stmt::Nop, Stmt::Nop]),
+            ],
+            vec![Stmt::Nop],
+        ),
+    ])
+}
+
+/// Loads the counter into `r0` and `1` into `r1`, then runs `body` `ITERATIONS` times.
+fn register_counted<F>(constants: &[Value], body: F) -> register::bytecode::Pool
+where
+    F: FnOnce(&mut register::bytecode::Pool),
+{
+    let mut pool = register::bytecode::Pool::default();
+    pool.push_literal(0, ITERATIONS);
+    pool.push_literal(1, 1);
+    for (register, constant) in (2..).zip(constants) {
+        pool.push_const(register, constant.clone());
+    }
+    pool.push_while_loop(
+        |condition| {
+            condition.push_binop(BinOp::Sub, 0, 0, 1);
+            0
+        },
+        body,
+    );
+    pool
+}
+
+fn register_arithmetic() -> register::bytecode::Pool {
+    let constants = [3.into(), 7.into(), 0.5.into(), 11.into()];
+    register_counted(&constants, |body| {
+        body.push_binop(BinOp::Mul, 8, 0, 2);
+        body.push_binop(BinOp::Add, 8, 8, 3);
+        body.push_binop(BinOp::Mul, 8, 8, 4);
+        body.push_binop(BinOp::Mod, 8, 8, 5);
+    })
+}
+
+fn register_strings() -> register::bytecode::Pool {
+    let constants = ["Hello, ".into(), "World!".into(), 4.into(), "".into()];
+    register_counted(&constants, |body| {
+        body.push_binop(BinOp::Add, 8, 2, 3);
+        body.push_binop(BinOp::Mul, 8, 8, 4);
+        body.push_binop(BinOp::Ne, 8, 8, 5);
+    })
+}
+
+fn register_branches() -> register::bytecode::Pool {
+    let constants = [3.into(), 2.into()];
+    register_counted(&constants, |body| {
+        body.push_binop(BinOp::Mod, 8, 0, 2);
+        body.push_if_or_else(
+            8,
+            |body| {
+                body.push_binop(BinOp::LT, 8, 0, 3);
+                body.push_if_or_else(8, register::bytecode::Pool::push_nop, |or_else| {
+                    or_else.push_nop();
+                    or_else.push_nop();
+                });
+            },
+            register::bytecode::Pool::push_nop,
+        );
+    })
+}
+
+struct Measurement {
+    instructions: usize,
+    elapsed: Duration,
+    allocations: usize,
+}
+
+/// Keeps the fastest of `RUNS` runs, `run` returns the number of executed instructions.
+fn measure<F: FnMut() -> usize>(mut run: F) -> Measurement {
+    let mut best: Option<Measurement> = None;
+    for _ in 0..RUNS {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+
+        let instructions = run();
+
+        let measurement = Measurement {
+            instructions,
+            elapsed: start.elapsed(),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        };
+        if best
+            .as_ref()
+            .is_none_or(|best| measurement.elapsed < best.elapsed)
+        {
+            best = Some(measurement);
+        }
+    }
+    best.unwrap()
+}
+
+fn run_stack<M: Machine>(pool: &M::Pool) -> usize {
+    let mut vm = M::new(pool);
+    let mut instructions = 0;
+    while !vm.is_finished() {
+        vm.run_next().unwrap();
+        instructions += 1;
+    }
+    instructions
+}
+
+fn run_threaded(code: &threaded::code::Code) -> usize {
+    let mut vm = threaded::vm::Vm::new(code);
+    let mut instructions = 0;
+    while !vm.is_finished() {
+        vm.run_next().unwrap();
+        instructions += 1;
+    }
+    instructions
+}
+
+fn run_register(pool: &register::bytecode::Pool) -> usize {
+    let mut vm = register::vm::Vm::new(pool);
+    let mut instructions = 0;
+    while vm.head < vm.bytes.len() {
+        vm.run_next().unwrap();
+        instructions += 1;
+    }
+    instructions
+}
+
+/// Prints a row and returns the number of executed instructions.
+fn report(workload: &str, vm: &str, measurement: &Measurement) -> usize {
+    #[allow(clippy::cast_precision_loss)]
+    let per_second = measurement.instructions as f64 / measurement.elapsed.as_secs_f64();
+    println!(
+        "{workload:<12} {vm:<16} {:>10} {:>12.2?} {:>14.0} {:>12}",
+        measurement.instructions, measurement.elapsed, per_second, measurement.allocations
+    );
+    measurement.instructions
+}
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    let workloads: [(&str, Workload, RegisterWorkload); 3] = [
+        ("arithmetic", arithmetic, register_arithmetic),
+        ("strings", strings, register_strings),
+        ("branches", branches, register_branches),
+    ];
+
+    println!(
+        "{:<12} {:<16} {:>10} {:>12} {:>14} {:>12}",
+        "workload", "vm", "instrs", "time", "instrs/s", "allocs"
+    );
+    for (name, workload, register_workload) in workloads {
+        if filter
+            .as_ref()
+            .is_some_and(|filter| !name.contains(filter.as_str()))
+        {
+            continue;
+        }
+        let program = workload();
+
+        let mut pool: two_byte::bytecode::Pool = program::lower(&program);
+        pool.relax();
+        let mut stack_runs = vec![
+            report(
+                name,
+                "two_byte",
+                &measure(|| run_stack::<two_byte::vm::Vm>(&pool)),
+            ),
+            report(
+                name,
+                "two_byte packed",
+                &measure(|| run_stack::<two_byte::vm::Vm<Packed>>(&pool)),
+            ),
+        ];
+
+        let mut pool: variable_length::bytecode::Pool = program::lower(&program);
+        pool.relax();
+        stack_runs.push(report(
+            name,
+            "variable_length",
+            &measure(|| run_stack::<variable_length::vm::Vm>(&pool)),
+        ));
+        stack_runs.push(report(
+            name,
+            "var_len packed",
+            &measure(|| run_stack::<variable_length::vm::Vm<Packed>>(&pool)),
+        ));
+
+        let code = threaded::code::Code::from_pool(&pool).unwrap();
+        stack_runs.push(report(name, "threaded", &measure(|| run_threaded(&code))));
+
+        // The stack engines lower the same program, so a differing count means
+        // one of them is timing different work.
+        assert!(
+            stack_runs.windows(2).all(|pair| pair[0] == pair[1]),
+            "{name}: stack engines executed {stack_runs:?} instructions"
+        );
+
+        let pool = register_workload();
+        report(name, "register", &measure(|| run_register(&pool)));
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{bigint::BigInt, Error, Value};
+
+/// What integer arithmetic does when the exact result doesn't fit an `i64`.
+///
+/// Machines default to `Unbounded`, and every mode behaves the same in every build profile.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticMode {
+    /// Fails with `Error::IntegerOverflow`.
+    Checked,
+    /// Wraps around in two's complement.
+    Wrapping,
+    /// Clamps to `i64::MIN` or `i64::MAX`.
+    Saturating,
+    /// Rounds the exact result to a float.
+    Promote,
+    /// Keeps the exact result as a `Value::BigInt`.
+    #[default]
+    Unbounded,
+}
+
+impl ArithmeticMode {
+    /// Picks the result for this mode, `exact` being `None` when the operation overflowed.
+    ///
+    /// `big` computes the exact result and is only called on overflow.
+    pub fn resolve(self, exact: Option<i64>, big: impl FnOnce() -> BigInt) -> Result<Value, Error> {
+        if let Some(int) = exact {
+            return Ok(Value::Int(int));
+        }
+        Ok(match self {
+            Self::Checked => return Err(Error::IntegerOverflow),
+            Self::Wrapping => Value::Int(big().wrapping_to_i64()),
+            Self::Saturating => Value::Int(if big().is_negative() {
+                i64::MIN
+            } else {
+                i64::MAX
+            }),
+            Self::Promote => Value::Float(big().to_f64()),
+            Self::Unbounded => Value::big(big()),
+        })
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{instr::Instr, BinOp, Error, UnaryOp, Value};
+use std::fmt;
+
+/// A bytecode builder that front ends can target without caring about the encoding.
+///
+/// Jump helpers return an opaque handle that is only meaningful to `patch_jump`
+/// of the same pool, since each encoding places its operands differently.
+pub trait Assembler: Default + fmt::Display {
+    type Vm: Machine<Pool = Self>;
+
+    fn len(&self) -> usize;
+    #[must_use]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push_nop(&mut self);
+    fn push_dup(&mut self);
+    fn push_pop(&mut self);
+    fn push_swap(&mut self);
+    fn push_rot3(&mut self);
+    fn push_over(&mut self);
+    fn push_pop_n(&mut self, len: usize);
+    fn push_print(&mut self);
+    fn push_to_str(&mut self);
+    fn push_to_int(&mut self);
+    fn push_to_float(&mut self);
+    /// Pushes a `Format` of `template`, adding it to the constants.
+    fn push_format(&mut self, template: &str);
+    fn push_binop(&mut self, binop: BinOp);
+    fn push_unaryop(&mut self, op: UnaryOp);
+    fn push_const(&mut self, value: Value) -> usize;
+    #[inline]
+    fn push_literal<V: Into<Value>>(&mut self, value: V) -> usize {
+        self.push_const(value.into())
+    }
+
+    fn push_build_list(&mut self, len: usize);
+    fn push_index(&mut self);
+    fn push_store_index(&mut self);
+    fn push_len(&mut self);
+    fn push_append(&mut self);
+    fn push_build_map(&mut self, len: usize);
+    fn push_get_item(&mut self);
+    fn push_set_item(&mut self);
+    fn push_has_key(&mut self);
+    fn push_keys(&mut self);
+
+    fn push_jump(&mut self, pos: usize) -> usize;
+    fn push_pop_jump_if_false(&mut self, pos: usize) -> usize;
+    fn push_pop_jump_if_true(&mut self, pos: usize) -> usize;
+    fn push_jump_if_false_or_pop(&mut self, pos: usize) -> usize;
+    fn push_jump_if_true_or_pop(&mut self, pos: usize) -> usize;
+    fn patch_jump(&mut self, jump: usize);
+    /// Splices `other` onto the end, merging its constants into this pool's table.
+    fn append(&mut self, other: Self);
+
+    fn constants(&self) -> &[Value];
+    fn decode(&self) -> Result<Vec<Instr>, Error>;
+    /// Encodes `instrs`, whose `LoadConst` and `Format` indices refer to `constants`.
+    fn from_instrs(instrs: &[Instr], constants: Vec<Value>) -> Self;
+
+    #[inline]
+    fn push_if<F>(&mut self, body: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let jump = self.push_pop_jump_if_false(0);
+        body(self);
+        self.patch_jump(jump);
+    }
+    #[inline]
+    fn push_if_or_else<F1, F2>(&mut self, body: F1, or_else: F2)
+    where
+        F1: FnOnce(&mut Self),
+        F2: FnOnce(&mut Self),
+    {
+        let jump_if = self.push_pop_jump_if_false(0);
+        body(self);
+        let jump_else = self.push_jump(0);
+        self.patch_jump(jump_if);
+        or_else(self);
+        self.patch_jump(jump_else);
+    }
+    /// Leaves `lhs` if it is false and `rhs` otherwise, only running `rhs` when `lhs` is true.
+    #[inline]
+    fn push_and<F1, F2>(&mut self, lhs: F1, rhs: F2)
+    where
+        F1: FnOnce(&mut Self),
+        F2: FnOnce(&mut Self),
+    {
+        lhs(self);
+        let jump = self.push_jump_if_false_or_pop(0);
+        rhs(self);
+        self.patch_jump(jump);
+    }
+    /// Leaves `lhs` if it is true and `rhs` otherwise, only running `rhs` when `lhs` is false.
+    #[inline]
+    fn push_or<F1, F2>(&mut self, lhs: F1, rhs: F2)
+    where
+        F1: FnOnce(&mut Self),
+        F2: FnOnce(&mut Self),
+    {
+        lhs(self);
+        let jump = self.push_jump_if_true_or_pop(0);
+        rhs(self);
+        self.patch_jump(jump);
+    }
+    #[inline]
+    fn push_loop<F>(&mut self, body: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let start = self.len();
+        body(self);
+        self.push_jump(start);
+    }
+    #[inline]
+    fn push_while_loop<F1, F2>(&mut self, condition: F1, body: F2)
+    where
+        F1: FnOnce(&mut Self),
+        F2: FnOnce(&mut Self),
+    {
+        let start = self.len();
+        condition(self);
+        let jump = self.push_pop_jump_if_false(0);
+        body(self);
+        self.push_jump(start);
+        self.patch_jump(jump);
+    }
+}
+
+/// A virtual machine that executes the bytecode produced by its `Pool`.
+///
+/// Machines keep their own handle on the program, so they and their results
+/// can outlive the pool they were created from.
+pub trait Machine: Sized {
+    type Pool: Assembler;
+
+    fn new(pool: &Self::Pool) -> Self;
+    fn is_finished(&self) -> bool;
+    fn run_next(&mut self) -> Result<(), Error>;
+    fn into_stack(self) -> Vec<Value>;
+
+    fn run(&mut self) -> Result<(), Error> {
+        while !self.is_finished() {
+            self.run_next()?;
+        }
+        Ok(())
+    }
+
+    fn create_and_run(pool: &Self::Pool) -> Result<Vec<Value>, Error> {
+        let mut vm = Self::new(pool);
+        vm.run()?;
+        Ok(vm.into_stack())
+    }
+}
+
+/// Runs `pool` on the machine belonging to its encoding.
+pub fn run<A: Assembler>(pool: &A) -> Result<Vec<Value>, Error> {
+    A::Vm::create_and_run(pool)
+}
@@ -0,0 +1,520 @@
+#![allow(clippy::cast_possible_truncation)]
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, BitAnd, BitOr, BitXor, Mul, Neg, Not, Shl, Shr, Sub},
+    str::FromStr,
+};
+
+/// An arbitrary precision integer, stored as a sign and a magnitude of
+/// little-endian 32-bit limbs without trailing zero limbs.
+///
+/// Zero has no limbs and is never negative, so every number has exactly one
+/// representation and the derived `Eq` and `Hash` agree with its value.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+    #[must_use]
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+    #[must_use]
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+    /// The number of bits in the magnitude.
+    #[must_use]
+    pub fn bits(&self) -> u64 {
+        self.magnitude.last().map_or(0, |top| {
+            (self.magnitude.len() as u64 - 1) * 32 + u64::from(32 - top.leading_zeros())
+        })
+    }
+    #[must_use]
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let magnitude = self.low_u64();
+        if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+    /// The low 64 bits of the two's complement representation.
+    #[must_use]
+    pub fn wrapping_to_i64(&self) -> i64 {
+        let low = self.low_u64().cast_signed();
+        if self.negative {
+            low.wrapping_neg()
+        } else {
+            low
+        }
+    }
+    /// The nearest float, or an infinity when out of range.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, &limb| acc * 4_294_967_296.0 + f64::from(limb));
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+    /// The integer part of `float`, or `None` for infinities and NaN.
+    #[must_use]
+    pub fn from_f64(float: f64) -> Option<Self> {
+        if !float.is_finite() {
+            return None;
+        }
+        let bits = float.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff).cast_signed() - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let magnitude = if exponent >= 0 {
+            &Self::from(mantissa.cast_signed()) << exponent.unsigned_abs() as u32
+        } else if exponent > -64 {
+            Self::from((mantissa >> exponent.unsigned_abs()).cast_signed())
+        } else {
+            // Subnormals and anything else below one.
+            Self::default()
+        };
+        Some(if float < 0.0 { -&magnitude } else { magnitude })
+    }
+    /// Truncating division like `i64`, so the remainder has the sign of `self`.
+    ///
+    /// Returns `None` when dividing by zero.
+    #[must_use]
+    pub fn div_rem(&self, divisor: &Self) -> Option<(Self, Self)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &divisor.magnitude);
+        Some((
+            Self::new(self.negative != divisor.negative, quotient),
+            Self::new(self.negative, remainder),
+        ))
+    }
+    #[must_use]
+    pub fn pow(&self, mut exp: u64) -> Self {
+        let mut base = self.clone();
+        let mut result = Self::from(1);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+    fn low_u64(&self) -> u64 {
+        let limb = |index| u64::from(self.magnitude.get(index).copied().unwrap_or(0));
+        limb(0) | limb(1) << 32
+    }
+    /// The two's complement representation in `len` limbs, which must leave room for the sign bit.
+    fn to_twos_complement(&self, len: usize) -> Vec<u32> {
+        let mut limbs = self.magnitude.clone();
+        limbs.resize(len, 0);
+        if self.negative {
+            let mut borrow = true;
+            for limb in &mut limbs {
+                let (value, overflow) = limb.overflowing_sub(u32::from(borrow));
+                *limb = !value;
+                borrow = overflow;
+            }
+        }
+        limbs
+    }
+    fn from_twos_complement(mut limbs: Vec<u32>) -> Self {
+        let negative = limbs.last().is_some_and(|top| top >> 31 == 1);
+        if negative {
+            let mut carry = true;
+            for limb in &mut limbs {
+                let (value, overflow) = (!*limb).overflowing_add(u32::from(carry));
+                *limb = value;
+                carry = overflow;
+            }
+        }
+        Self::new(negative, limbs)
+    }
+    fn bitwise(&self, rhs: &Self, op: impl Fn(u32, u32) -> u32) -> Self {
+        let len = self.magnitude.len().max(rhs.magnitude.len()) + 1;
+        let lhs = self.to_twos_complement(len);
+        let rhs = rhs.to_twos_complement(len);
+        Self::from_twos_complement(lhs.iter().zip(&rhs).map(|(&l, &r)| op(l, r)).collect())
+    }
+}
+
+fn compare_magnitude(lhs: &[u32], rhs: &[u32]) -> Ordering {
+    lhs.len()
+        .cmp(&rhs.len())
+        .then_with(|| lhs.iter().rev().cmp(rhs.iter().rev()))
+}
+
+fn add_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let (long, short) = if lhs.len() >= rhs.len() {
+        (lhs, rhs)
+    } else {
+        (rhs, lhs)
+    };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0;
+    for (index, &limb) in long.iter().enumerate() {
+        let total = u64::from(limb) + u64::from(short.get(index).copied().unwrap_or(0)) + carry;
+        sum.push(total as u32);
+        carry = total >> 32;
+    }
+    sum.push(carry as u32);
+    sum
+}
+
+/// Subtracts `rhs` from `lhs`, which must not be smaller.
+fn sub_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(lhs.len());
+    let mut borrow = false;
+    for (index, &limb) in lhs.iter().enumerate() {
+        let (value, overflow) = limb.overflowing_sub(rhs.get(index).copied().unwrap_or(0));
+        let (value, underflow) = value.overflowing_sub(u32::from(borrow));
+        difference.push(value);
+        borrow = overflow || underflow;
+    }
+    difference
+}
+
+fn mul_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; lhs.len() + rhs.len()];
+    for (i, &l) in lhs.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &r) in rhs.iter().enumerate() {
+            let total = u64::from(l) * u64::from(r) + u64::from(product[i + j]) + carry;
+            product[i + j] = total as u32;
+            carry = total >> 32;
+        }
+        product[i + rhs.len()] = carry as u32;
+    }
+    product
+}
+
+/// Divides by a single limb, returning the remainder.
+fn div_rem_limb(magnitude: &mut [u32], divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for limb in magnitude.iter_mut().rev() {
+        let current = remainder << 32 | u64::from(*limb);
+        *limb = (current / u64::from(divisor)) as u32;
+        remainder = current % u64::from(divisor);
+    }
+    remainder as u32
+}
+
+/// Long division a limb at a time, Knuth's algorithm D.
+fn div_rem_magnitude(lhs: &[u32], rhs: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = *rhs {
+        let mut quotient = lhs.to_vec();
+        let remainder = div_rem_limb(&mut quotient, divisor);
+        return (quotient, vec![remainder]);
+    }
+    if compare_magnitude(lhs, rhs) == Ordering::Less {
+        return (vec![], lhs.to_vec());
+    }
+    // With the top bit of the divisor set, a quotient limb estimated from the
+    // top limbs alone is at most two too large.
+    let shift = rhs[rhs.len() - 1].leading_zeros();
+    let divisor = shl_magnitude(rhs, shift);
+    let mut remainder = shl_magnitude(lhs, shift);
+    remainder.resize(lhs.len() + 1, 0);
+    let len = divisor.len();
+    let (top, next) = (u64::from(divisor[len - 1]), u64::from(divisor[len - 2]));
+    let mut quotient = vec![0u32; lhs.len() - len + 1];
+    for j in (0..quotient.len()).rev() {
+        let numerator = u64::from(remainder[j + len]) << 32 | u64::from(remainder[j + len - 1]);
+        let mut estimate = numerator / top;
+        let mut rest = numerator % top;
+        while estimate > u64::from(u32::MAX)
+            || estimate * next > (rest << 32 | u64::from(remainder[j + len - 2]))
+        {
+            estimate -= 1;
+            rest += top;
+            if rest > u64::from(u32::MAX) {
+                break;
+            }
+        }
+        // Subtract `estimate * divisor` from the window of the remainder it divides.
+        let window = &mut remainder[j..=j + len];
+        let (mut carry, mut borrow) = (0u64, false);
+        for (limb, &digit) in window.iter_mut().zip(&divisor) {
+            let product = estimate * u64::from(digit) + carry;
+            carry = product >> 32;
+            let (difference, under) = limb.overflowing_sub(product as u32);
+            let (difference, under_borrow) = difference.overflowing_sub(u32::from(borrow));
+            *limb = difference;
+            borrow = under || under_borrow;
+        }
+        let (difference, under) = window[len].overflowing_sub(carry as u32);
+        let (difference, under_borrow) = difference.overflowing_sub(u32::from(borrow));
+        window[len] = difference;
+        // Rarely the estimate is still one too large, so add the divisor back.
+        if under || under_borrow {
+            estimate -= 1;
+            let mut carry = 0u64;
+            for (limb, &digit) in window.iter_mut().zip(&divisor) {
+                let sum = u64::from(*limb) + u64::from(digit) + carry;
+                *limb = sum as u32;
+                carry = sum >> 32;
+            }
+            window[len] = window[len].wrapping_add(carry as u32);
+        }
+        quotient[j] = estimate as u32;
+    }
+    (quotient, shr_magnitude(&remainder[..len], shift))
+}
+
+fn shl_magnitude(magnitude: &[u32], shift: u32) -> Vec<u32> {
+    let (limbs, bits) = ((shift / 32) as usize, shift % 32);
+    let mut shifted = vec![0u32; limbs];
+    let mut carry = 0;
+    for &limb in magnitude {
+        shifted.push(limb << bits | carry);
+        carry = if bits == 0 { 0 } else { limb >> (32 - bits) };
+    }
+    shifted.push(carry);
+    trim(shifted)
+}
+
+fn shr_magnitude(magnitude: &[u32], shift: u32) -> Vec<u32> {
+    let (limbs, bits) = ((shift / 32) as usize, shift % 32);
+    let Some(magnitude) = magnitude.get(limbs..) else {
+        return vec![];
+    };
+    let mut shifted: Vec<u32> = magnitude.iter().map(|limb| limb >> bits).collect();
+    if bits != 0 {
+        for (index, &next) in magnitude.iter().enumerate().skip(1) {
+            shifted[index - 1] |= next << (32 - bits);
+        }
+    }
+    trim(shifted)
+}
+
+fn trim(mut magnitude: Vec<u32>) -> Vec<u32> {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+    magnitude
+}
+
+impl From<i64> for BigInt {
+    fn from(int: i64) -> Self {
+        let magnitude = int.unsigned_abs();
+        Self::new(int < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: Self) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::new(
+                self.negative,
+                add_magnitude(&self.magnitude, &rhs.magnitude),
+            );
+        }
+        match compare_magnitude(&self.magnitude, &rhs.magnitude) {
+            Ordering::Less => {
+                BigInt::new(rhs.negative, sub_magnitude(&rhs.magnitude, &self.magnitude))
+            }
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitude(&self.magnitude, &rhs.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: Self) -> BigInt {
+        self + &-rhs
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: Self) -> BigInt {
+        BigInt::new(
+            self.negative != rhs.negative,
+            mul_magnitude(&self.magnitude, &rhs.magnitude),
+        )
+    }
+}
+
+impl Shl<u32> for &BigInt {
+    type Output = BigInt;
+
+    fn shl(self, shift: u32) -> BigInt {
+        BigInt::new(self.negative, shl_magnitude(&self.magnitude, shift))
+    }
+}
+
+/// Rounds towards negative infinity like `i64`'s arithmetic shift.
+impl Shr<u32> for &BigInt {
+    type Output = BigInt;
+
+    fn shr(self, shift: u32) -> BigInt {
+        if !self.negative {
+            return BigInt::new(false, shr_magnitude(&self.magnitude, shift));
+        }
+        let one = BigInt::from(1);
+        let below = &-self - &one;
+        &-&BigInt::new(false, shr_magnitude(&below.magnitude, shift)) - &one
+    }
+}
+
+impl BitAnd for &BigInt {
+    type Output = BigInt;
+
+    fn bitand(self, rhs: Self) -> BigInt {
+        self.bitwise(rhs, |lhs, rhs| lhs & rhs)
+    }
+}
+
+impl BitOr for &BigInt {
+    type Output = BigInt;
+
+    fn bitor(self, rhs: Self) -> BigInt {
+        self.bitwise(rhs, |lhs, rhs| lhs | rhs)
+    }
+}
+
+impl BitXor for &BigInt {
+    type Output = BigInt;
+
+    fn bitxor(self, rhs: Self) -> BigInt {
+        self.bitwise(rhs, |lhs, rhs| lhs ^ rhs)
+    }
+}
+
+/// `!x == -x - 1`, as for `i64`.
+impl Not for &BigInt {
+    type Output = BigInt;
+
+    fn not(self) -> BigInt {
+        &-self - &BigInt::from(1)
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const CHUNK: u32 = 1_000_000_000;
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        let mut magnitude = self.magnitude.clone();
+        let mut chunks = vec![];
+        while !magnitude.is_empty() {
+            chunks.push(div_rem_limb(&mut magnitude, CHUNK));
+            magnitude = trim(magnitude);
+        }
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigIntError;
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid integer literal")
+    }
+}
+
+impl std::error::Error for ParseBigIntError {}
+
+/// Parses an optionally signed run of decimal digits, the inverse of `Display`.
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(str: &str) -> Result<Self, ParseBigIntError> {
+        let (negative, digits) = match str.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, str.strip_prefix('+').unwrap_or(str)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(ParseBigIntError);
+        }
+        let mut magnitude: Vec<u32> = vec![];
+        for byte in digits.bytes() {
+            let mut carry = u64::from(byte - b'0');
+            for limb in &mut magnitude {
+                let total = u64::from(*limb) * 10 + carry;
+                *limb = total as u32;
+                carry = total >> 32;
+            }
+            if carry != 0 {
+                magnitude.push(carry as u32);
+            }
+        }
+        Ok(Self::new(negative, magnitude))
+    }
+}
@@ -1,5 +1,7 @@
+use crate::Error;
+
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinOp {
     Add = 0,
     Sub,
@@ -13,4 +15,32 @@ pub enum BinOp {
     GT,
     Eq,
     Ne,
+
+    BitAnd,
+    BitOr,
+    BitXor,
+    /// Shifts an int left, bits shifted out overflow according to the `ArithmeticMode`.
+    Shl,
+    /// Shifts an int right, keeping its sign.
+    Shr,
+    /// Raises to a power, an int to a negative int power gives a float.
+    Pow,
+}
+
+impl BinOp {
+    pub const COUNT: u8 = Self::Pow as u8 + 1;
+}
+
+/// Bytecode stores operators as bytes, so decoding one must not trust the byte.
+impl TryFrom<u8> for BinOp {
+    type Error = Error;
+
+    fn try_from(byte: u8) -> Result<Self, Error> {
+        if byte < Self::COUNT {
+            // SAFETY: `BinOp` is `repr(u8)` with contiguous discriminants below `COUNT`.
+            Ok(unsafe { std::mem::transmute::<u8, BinOp>(byte) })
+        } else {
+            Err(Error::InvalidOperator(byte))
+        }
+    }
 }
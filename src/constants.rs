@@ -0,0 +1,78 @@
+use crate::{bigint::BigInt, Value};
+use std::{collections::HashMap, ops::Deref, rc::Rc};
+
+/// What makes two constants interchangeable. Floats compare by bit pattern, so
+/// every `NaN` literal shares a slot while `0.0` and `-0.0` get their own.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Identity {
+    Int(i64),
+    BigInt(Rc<BigInt>),
+    Float(u64),
+    Str(Rc<str>),
+    /// Lists and maps are mutable, so only the very same one is interchangeable.
+    List(usize),
+    Map(usize),
+}
+
+impl From<&Value> for Identity {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Int(int) => Self::Int(*int),
+            Value::BigInt(big) => Self::BigInt(big.clone()),
+            Value::Float(float) => Self::Float(float.to_bits()),
+            Value::Str(str) => Self::Str(str.clone()),
+            Value::List(items) => Self::List(Rc::as_ptr(items) as usize),
+            Value::Map(entries) => Self::Map(Rc::as_ptr(entries) as usize),
+        }
+    }
+}
+
+/// A constant table shared by every pool, deduplicating through a hash index
+/// so that emitting a program stays linear in its number of literals.
+///
+/// This also makes it the pool's string interner: every distinct string is
+/// stored once and its key shares the allocation of the stored `Rc<str>`.
+#[derive(Debug, Clone, Default)]
+pub struct Constants {
+    values: Vec<Value>,
+    indices: HashMap<Identity, usize>,
+}
+
+impl Constants {
+    /// Returns the index of `value`, adding it unless an identical constant exists.
+    pub fn insert(&mut self, value: Value) -> usize {
+        let len = self.values.len();
+        let index = *self.indices.entry(Identity::from(&value)).or_insert(len);
+        if index == len {
+            self.values.push(value);
+        }
+        index
+    }
+    #[must_use]
+    pub fn find(&self, value: &Value) -> Option<usize> {
+        self.indices.get(&Identity::from(value)).copied()
+    }
+    #[must_use]
+    pub fn into_vec(self) -> Vec<Value> {
+        self.values
+    }
+}
+
+impl From<Vec<Value>> for Constants {
+    /// Keeps every index as is, duplicates included, so that decoded code
+    /// stays valid. Later inserts resolve to the first of the duplicates.
+    fn from(values: Vec<Value>) -> Self {
+        let mut indices = HashMap::with_capacity(values.len());
+        for (index, value) in values.iter().enumerate() {
+            indices.entry(Identity::from(value)).or_insert(index);
+        }
+        Self { values, indices }
+    }
+}
+
+impl Deref for Constants {
+    type Target = [Value];
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
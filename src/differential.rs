@@ -0,0 +1,148 @@
+use crate::{
+    generator::{shrink, Generator},
+    packed::Packed,
+    program,
+    program::Stmt,
+    threaded, two_byte, variable_length, Assembler, Error, Machine, Value,
+};
+use std::{fmt, ops::Range};
+
+/// The number of instructions a machine may execute before `check` gives up on it.
+pub const FUEL: usize = 10_000;
+
+/// The final stack or error of a run, or `None` if the machine ran out of fuel.
+pub type Outcome = Option<Result<Vec<Value>, Error>>;
+
+/// The results of running the same program on each engine, when they disagree.
+#[derive(Debug)]
+pub struct Divergence {
+    pub two_byte: String,
+    pub variable_length: String,
+    pub threaded: String,
+    pub packed: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "two_byte:\n{}", self.two_byte)?;
+        writeln!(f, "variable_length:\n{}", self.variable_length)?;
+        writeln!(f, "threaded:\n{}", self.threaded)?;
+        write!(f, "packed:\n{}", self.packed)
+    }
+}
+
+/// Lowers `program` into both encodings, runs each on its own `Vm` as well as
+/// the `threaded` engine and the `two_byte` machine on `Packed` values, and
+/// checks that they finish with identical stacks or identical errors.
+///
+/// Engines that all run out of `FUEL` are considered to agree.
+pub fn check(program: &[Stmt]) -> Result<(), Divergence> {
+    let two_byte: two_byte::bytecode::Pool = program::lower(program);
+    let mut variable_length: variable_length::bytecode::Pool = program::lower(program);
+    variable_length.relax();
+
+    let code = threaded::code::Code::from_pool(&variable_length);
+
+    let two_byte_outcome = run_with_fuel(&two_byte);
+    let variable_length_outcome = run_with_fuel(&variable_length);
+    let threaded_outcome = match &code {
+        Ok(code) => run_threaded_with_fuel(code),
+        Err(err) => Some(Err(err.clone())),
+    };
+    let packed_outcome = run_machine_with_fuel::<two_byte::vm::Vm<Packed>>(&two_byte);
+
+    if same_outcome(&two_byte_outcome, &variable_length_outcome)
+        && same_outcome(&variable_length_outcome, &threaded_outcome)
+        && same_outcome(&two_byte_outcome, &packed_outcome)
+    {
+        return Ok(());
+    }
+    Err(Divergence {
+        two_byte: describe(&two_byte, &two_byte_outcome),
+        variable_length: describe(&variable_length, &variable_length_outcome),
+        threaded: describe(&"", &threaded_outcome),
+        packed: describe(&"", &packed_outcome),
+    })
+}
+
+/// Like `check`, but panics with both disassemblies on divergence.
+pub fn assert_agree(program: &[Stmt]) {
+    if let Err(divergence) = check(program) {
+        panic!("machines diverged\n{divergence}");
+    }
+}
+
+/// Checks a generated program for every seed, shrinking the first divergent
+/// program to a minimal reproduction and panicking with it.
+pub fn fuzz<F>(seeds: Range<u64>, mut generator: F)
+where
+    F: FnMut(u64) -> Generator,
+{
+    for seed in seeds {
+        let program = generator(seed).program();
+        if check(&program).is_ok() {
+            continue;
+        }
+        let program = shrink(program, |program| check(program).is_err());
+        let divergence = check(&program).unwrap_err();
+        panic!("machines diverged for seed {seed} on {program:?}\n{divergence}");
+    }
+}
+
+/// Runs `pool` for at most `FUEL` instructions.
+pub fn run_with_fuel<A: Assembler>(pool: &A) -> Outcome {
+    run_machine_with_fuel::<A::Vm>(pool)
+}
+
+/// Like `run_with_fuel`, but on a machine other than the encoding's default one.
+pub fn run_machine_with_fuel<M: Machine>(pool: &M::Pool) -> Outcome {
+    let mut vm = M::new(pool);
+    for _ in 0..FUEL {
+        if vm.is_finished() {
+            return Some(Ok(vm.into_stack()));
+        }
+        if let Err(err) = vm.run_next() {
+            return Some(Err(err));
+        }
+    }
+    None
+}
+
+fn run_threaded_with_fuel(code: &threaded::code::Code) -> Outcome {
+    let mut vm = threaded::vm::Vm::new(code);
+    for _ in 0..FUEL {
+        if vm.is_finished() {
+            return Some(Ok(vm.state.stack));
+        }
+        if let Err(err) = vm.run_next() {
+            return Some(Err(err));
+        }
+    }
+    None
+}
+
+fn describe(pool: &dyn fmt::Display, outcome: &Outcome) -> String {
+    match outcome {
+        Some(result) => format!("{pool}=> {result:?}"),
+        None => format!("{pool}=> ran out of fuel"),
+    }
+}
+
+fn same_outcome(lhs: &Outcome, rhs: &Outcome) -> bool {
+    match (lhs, rhs) {
+        (Some(Ok(lhs)), Some(Ok(rhs))) => {
+            lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(lhs, rhs)| identical(lhs, rhs))
+        }
+        (Some(Err(lhs)), Some(Err(rhs))) => lhs == rhs,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Compares floats bitwise, so `NaN` matches itself and `0.0` differs from `-0.0`.
+fn identical(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Float(lhs), Value::Float(rhs)) => lhs.to_bits() == rhs.to_bits(),
+        (lhs, rhs) => lhs == rhs,
+    }
+}
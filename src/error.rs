@@ -0,0 +1,98 @@
+use crate::{map::Key, BinOp, Value};
+use std::{fmt, io, rc::Rc};
+
+/// A runtime error raised while executing bytecode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    StackUnderflow,
+    InvalidOpCode(u8),
+    InvalidOperator(u8),
+    InvalidConstant(usize),
+    InvalidRegister(u8),
+    InvalidJump(usize),
+    UnexpectedEnd,
+    DivisionByZero,
+    IntegerOverflow,
+    InvalidShift(i64),
+    /// Repetition would build a list or string longer than this.
+    SizeLimit(usize),
+    UnsupportedOperands {
+        op: BinOp,
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    UnsupportedOperand {
+        op: &'static str,
+        operand: &'static str,
+    },
+    IndexOutOfBounds {
+        index: i64,
+        len: usize,
+    },
+    UnhashableKey(&'static str),
+    KeyNotFound(Key),
+    /// Storing a list or map into itself, directly or through a value it holds.
+    Cycle(&'static str),
+    /// A string that `ToInt` or `ToFloat` couldn't read as a number.
+    Parse {
+        text: Rc<str>,
+        into: &'static str,
+    },
+    /// `ToInt` of an infinity or NaN.
+    NonFinite(f64),
+    /// A `Format` template with an unescaped brace outside of a `{}`.
+    InvalidFormat(Rc<str>),
+    /// Writing to the machine's output failed.
+    Output(io::ErrorKind),
+}
+
+impl Error {
+    #[must_use]
+    pub fn unsupported(op: BinOp, lhs: &Value, rhs: &Value) -> Self {
+        Self::UnsupportedOperands {
+            op,
+            lhs: lhs.type_name(),
+            rhs: rhs.type_name(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StackUnderflow => write!(f, "stack underflow"),
+            Self::InvalidOpCode(byte) => write!(f, "invalid opcode {byte}"),
+            Self::InvalidOperator(byte) => write!(f, "invalid operator {byte}"),
+            Self::InvalidConstant(index) => write!(f, "no constant at index {index}"),
+            Self::InvalidRegister(register) => write!(f, "no register r{register}"),
+            Self::InvalidJump(target) => {
+                write!(f, "jump into the middle of an instruction at {target}")
+            }
+            Self::UnexpectedEnd => write!(f, "bytecode ends in the middle of an instruction"),
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::IntegerOverflow => write!(f, "integer overflow"),
+            Self::InvalidShift(count) => write!(f, "shift count {count} out of range 0..64"),
+            Self::SizeLimit(limit) => write!(f, "result longer than the limit of {limit}"),
+            Self::UnsupportedOperands { op, lhs, rhs } => {
+                write!(f, "unsupported operands for {op:?}: {lhs} and {rhs}")
+            }
+            Self::UnsupportedOperand { op, operand } => {
+                write!(f, "unsupported operand for {op}: {operand}")
+            }
+            Self::IndexOutOfBounds { index, len } => {
+                write!(f, "index {index} out of bounds for a list of {len}")
+            }
+            Self::UnhashableKey(type_name) => write!(f, "unhashable key of type {type_name}"),
+            Self::KeyNotFound(key) => write!(f, "key {key} not found"),
+            Self::Cycle(op) => write!(f, "{op} would store a list or map inside itself"),
+            Self::Parse { text, into } => write!(f, "cannot parse {text:?} as {into}"),
+            Self::NonFinite(float) => {
+                write!(f, "cannot convert {} to int", Value::Float(*float))
+            }
+            Self::InvalidFormat(template) => write!(f, "invalid format string {template:?}"),
+            Self::Output(kind) => write!(f, "failed to write output: {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,82 @@
+use crate::{Error, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Text(String),
+    Hole,
+}
+
+/// A format string for the `Format` instruction, split into text and `{}` placeholders.
+///
+/// Braces are escaped by doubling them, and any other brace is an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+    holes: usize,
+}
+
+impl Template {
+    /// The number of values `render` consumes.
+    #[must_use]
+    pub fn holes(&self) -> usize {
+        self.holes
+    }
+    /// Fills the placeholders in order with the `Display` of `args`, which
+    /// should yield one value per hole.
+    pub fn render(&self, args: impl IntoIterator<Item = Value>) -> Value {
+        let mut args = args.into_iter();
+        let mut rendered = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => rendered.push_str(text),
+                Piece::Hole => {
+                    if let Some(arg) = args.next() {
+                        rendered.push_str(&arg.to_string());
+                    }
+                }
+            }
+        }
+        Value::Str(rendered.into())
+    }
+}
+
+impl TryFrom<&str> for Template {
+    type Error = Error;
+
+    fn try_from(template: &str) -> Result<Self, Error> {
+        let mut pieces = vec![];
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(at) = rest.find(['{', '}']) {
+            text.push_str(&rest[..at]);
+            let brace = &rest[at..];
+            if brace.starts_with("{{") || brace.starts_with("}}") {
+                text.push_str(&brace[..1]);
+            } else if brace.starts_with("{}") {
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+                pieces.push(Piece::Hole);
+            } else {
+                return Err(Error::InvalidFormat(template.into()));
+            }
+            rest = &brace[2..];
+        }
+        text.push_str(rest);
+        pieces.push(Piece::Text(text));
+        let holes = pieces.iter().filter(|piece| **piece == Piece::Hole).count();
+        Ok(Self { pieces, holes })
+    }
+}
+
+impl TryFrom<&Value> for Template {
+    type Error = Error;
+
+    fn try_from(template: &Value) -> Result<Self, Error> {
+        match template {
+            Value::Str(template) => Self::try_from(&**template),
+            _ => Err(Error::UnsupportedOperand {
+                op: "Format",
+                operand: template.type_name(),
+            }),
+        }
+    }
+}
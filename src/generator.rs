@@ -0,0 +1,423 @@
+use crate::{map::Map, program::Stmt, BinOp, Value};
+
+/// A deterministic `SplitMix64` pseudo random number generator.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Returns a number in `0..bound`.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+    /// Returns a number in `low..=high`.
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    pub fn range(&mut self, low: i64, high: i64) -> i64 {
+        let span = high.abs_diff(low) + 1;
+        low.wrapping_add((self.next_u64() % span) as i64)
+    }
+    pub fn chance(&mut self, numerator: usize, denominator: usize) -> bool {
+        self.below(denominator) < numerator
+    }
+    pub fn choose<'s, T>(&mut self, items: &'s [T]) -> &'s T {
+        &items[self.below(items.len())]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Int,
+    Float,
+    Str,
+}
+
+const KINDS: [Kind; 3] = [Kind::Int, Kind::Float, Kind::Str];
+
+const BINOPS: [BinOp; 17] = [
+    BinOp::Add,
+    BinOp::Sub,
+    BinOp::Mul,
+    BinOp::Div,
+    BinOp::Mod,
+    BinOp::Pow,
+    BinOp::LE,
+    BinOp::LT,
+    BinOp::GE,
+    BinOp::GT,
+    BinOp::Eq,
+    BinOp::Ne,
+    BinOp::BitAnd,
+    BinOp::BitOr,
+    BinOp::BitXor,
+    BinOp::Shl,
+    BinOp::Shr,
+];
+
+const STRINGS: [&str; 5] = ["", "a", "ab", "hello", "Hello, World!"];
+
+const MAX_STR_LEN: usize = 256;
+
+/// Generates random programs that only apply `BinOp`s to operands they support,
+/// and whose loops always count down from a small literal, so every program terminates.
+///
+/// Runtime errors such as a division by zero are still possible.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    pub rng: Rng,
+    pub max_depth: usize,
+    pub max_stmts: usize,
+    /// Also emit `BinOp`s on operands that don't support them.
+    pub ill_typed: bool,
+}
+
+impl Generator {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            max_depth: 3,
+            max_stmts: 16,
+            ill_typed: false,
+        }
+    }
+    pub fn program(&mut self) -> Vec<Stmt> {
+        let mut stack = vec![];
+        let mut stmts = vec![];
+        for _ in 0..=self.rng.below(self.max_stmts) {
+            self.stmt(&mut stack, &mut stmts);
+        }
+        stmts
+    }
+    /// Emits a statement while tracking the kind and maximum string length of each stack slot.
+    fn stmt(&mut self, stack: &mut Vec<(Kind, usize)>, out: &mut Vec<Stmt>) {
+        match self.rng.below(6) {
+            0 => {
+                if let Some(&top) = stack.last() {
+                    out.push(Stmt::Dup);
+                    stack.push(top);
+                }
+            }
+            1 if stack.len() >= 2 => {
+                let (rhs, rhs_len) = stack[stack.len() - 1];
+                let (lhs, lhs_len) = stack[stack.len() - 2];
+                let op = *self.rng.choose(&BINOPS);
+                // `Str * Int`, powers and shifts are never picked here since the
+                // repeat count, exponent or shift count is unknown.
+                if literal_rhs(op).is_some() {
+                    return;
+                }
+                let Some(kind) = binop_kind(op, lhs, rhs) else {
+                    return;
+                };
+                let len = if kind == Kind::Str {
+                    lhs_len + rhs_len
+                } else {
+                    0
+                };
+                if len > MAX_STR_LEN {
+                    return;
+                }
+                stack.truncate(stack.len() - 2);
+                stack.push((kind, len));
+                out.push(Stmt::BinOp(op));
+            }
+            2 => out.extend(self.neutral(self.max_depth)),
+            3 => {
+                out.extend(self.counted_loop(self.max_depth));
+                stack.push((Kind::Int, 0));
+            }
+            _ => {
+                let kind = *self.rng.choose(&KINDS);
+                let len = self.expr(kind, self.max_depth, out);
+                stack.push((kind, len));
+            }
+        }
+    }
+    /// Emits code that pushes exactly one value of `kind`, returning its maximum string length.
+    fn expr(&mut self, kind: Kind, depth: usize, out: &mut Vec<Stmt>) -> usize {
+        if depth == 0 || self.rng.chance(1, 3) {
+            return self.literal(kind, out);
+        }
+        match self.rng.below(4) {
+            0 => {
+                let condition = *self.rng.choose(&KINDS);
+                self.expr(condition, depth - 1, out);
+                let mut body = vec![];
+                let mut or_else = vec![];
+                let body_len = self.expr(kind, depth - 1, &mut body);
+                let or_else_len = self.expr(kind, depth - 1, &mut or_else);
+                out.push(Stmt::IfElse(body, or_else));
+                body_len.max(or_else_len)
+            }
+            1 if kind == Kind::Str => {
+                let start = out.len();
+                let len = self.expr(Kind::Str, depth - 1, out);
+                let count = self.rng.range(-1, 3);
+                let repeated = len * usize::try_from(count).unwrap_or(0);
+                if repeated > MAX_STR_LEN {
+                    out.truncate(start);
+                    return self.literal(kind, out);
+                }
+                let count = Stmt::Literal(Value::Int(count));
+                if self.rng.chance(1, 2) {
+                    out.push(count);
+                } else {
+                    out.insert(start, count);
+                }
+                out.push(Stmt::BinOp(BinOp::Mul));
+                repeated
+            }
+            _ => {
+                let start = out.len();
+                let (op, lhs, rhs) = self.binop_for(kind);
+                let lhs_len = self.expr(lhs, depth - 1, out);
+                let rhs_len = match literal_rhs(op) {
+                    Some((low, high)) => {
+                        out.push(Stmt::Literal(Value::Int(self.rng.range(low, high))));
+                        0
+                    }
+                    None => self.expr(rhs, depth - 1, out),
+                };
+                let len = if kind == Kind::Str {
+                    lhs_len + rhs_len
+                } else {
+                    0
+                };
+                if len > MAX_STR_LEN {
+                    out.truncate(start);
+                    return self.literal(kind, out);
+                }
+                out.push(Stmt::BinOp(op));
+                len
+            }
+        }
+    }
+    /// Picks an operator and operand kinds producing `kind`.
+    fn binop_for(&mut self, kind: Kind) -> (BinOp, Kind, Kind) {
+        loop {
+            let op = *self.rng.choose(&BINOPS);
+            let lhs = *self.rng.choose(&KINDS);
+            let rhs = *self.rng.choose(&KINDS);
+            if op == BinOp::Mul && lhs != rhs && (lhs == Kind::Str || rhs == Kind::Str) {
+                continue;
+            }
+            if literal_rhs(op).is_some() && rhs != Kind::Int {
+                continue;
+            }
+            if self.ill_typed && self.rng.chance(1, 8) {
+                return (op, lhs, rhs);
+            }
+            if binop_kind(op, lhs, rhs) == Some(kind) {
+                return (op, lhs, rhs);
+            }
+        }
+    }
+    fn literal(&mut self, kind: Kind, out: &mut Vec<Stmt>) -> usize {
+        let (value, len) = match kind {
+            Kind::Int => {
+                let int = match self.rng.below(8) {
+                    0 => *self.rng.choose(&[0, i64::MIN, i64::MAX]),
+                    _ => self.rng.range(-10, 10),
+                };
+                (Value::Int(int), 0)
+            }
+            Kind::Float => {
+                let float = match self.rng.below(8) {
+                    0 => *self
+                        .rng
+                        .choose(&[f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0]),
+                    #[allow(clippy::cast_precision_loss)]
+                    _ => self.rng.range(-40, 40) as f64 / 4.0,
+                };
+                (Value::Float(float), 0)
+            }
+            Kind::Str => {
+                let str = *self.rng.choose(&STRINGS);
+                (Value::from(str), str.len())
+            }
+        };
+        out.push(Stmt::Literal(value));
+        len
+    }
+    /// Emits code that leaves the stack as it found it.
+    fn neutral(&mut self, depth: usize) -> Vec<Stmt> {
+        let mut out = vec![];
+        if depth == 0 {
+            out.push(Stmt::Nop);
+            return out;
+        }
+        match self.rng.below(4) {
+            0 => out.push(Stmt::Nop),
+            1 => {
+                let condition = *self.rng.choose(&KINDS);
+                self.expr(condition, depth - 1, &mut out);
+                let body = self.neutral_block(depth - 1);
+                out.push(Stmt::If(body));
+            }
+            2 => {
+                let condition = *self.rng.choose(&KINDS);
+                self.expr(condition, depth - 1, &mut out);
+                let body = self.neutral_block(depth - 1);
+                let or_else = self.neutral_block(depth - 1);
+                out.push(Stmt::IfElse(body, or_else));
+            }
+            _ => {
+                out.extend(self.counted_loop(depth - 1));
+                // Consume the exhausted counter.
+                out.push(Stmt::If(vec![]));
+            }
+        }
+        out
+    }
+    fn neutral_block(&mut self, depth: usize) -> Vec<Stmt> {
+        (0..self.rng.below(3))
+            .flat_map(|_| self.neutral(depth))
+            .collect()
+    }
+    /// Emits a loop that counts a small literal down to zero, leaving the zero on the stack.
+    fn counted_loop(&mut self, depth: usize) -> Vec<Stmt> {
+        let count = self.rng.range(1, 4);
+        vec![
+            Stmt::Literal(Value::Int(count)),
+            Stmt::While(
+                vec![
+                    Stmt::Literal(Value::Int(1)),
+                    Stmt::BinOp(BinOp::Sub),
+                    Stmt::Dup,
+                ],
+                self.neutral_block(depth),
+            ),
+        ]
+    }
+}
+
+/// The range of the `Int` literal emitted as the right operand of `op`, if it
+/// can't be an arbitrary expression.
+///
+/// A negative exponent would turn an `Int` power into a `Float`, and a shift count
+/// outside of `0..64` is an error.
+fn literal_rhs(op: BinOp) -> Option<(i64, i64)> {
+    match op {
+        BinOp::Pow => Some((0, 3)),
+        BinOp::Shl | BinOp::Shr => Some((0, 63)),
+        _ => None,
+    }
+}
+
+/// The kind produced by `op`, if it supports the operands.
+fn binop_kind(op: BinOp, lhs: Kind, rhs: Kind) -> Option<Kind> {
+    let is_number = |kind| matches!(kind, Kind::Int | Kind::Float);
+    match (op, lhs, rhs) {
+        (BinOp::Eq | BinOp::Ne, _, _) | (_, Kind::Int, Kind::Int) => Some(Kind::Int),
+        (BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr, _, _) => None,
+        (BinOp::LE | BinOp::LT | BinOp::GE | BinOp::GT, _, _) => (is_number(lhs) && is_number(rhs)
+            || lhs == Kind::Str && rhs == Kind::Str)
+            .then_some(Kind::Int),
+        (_, Kind::Int | Kind::Float, Kind::Int | Kind::Float) => Some(Kind::Float),
+        (BinOp::Add, Kind::Str, Kind::Str) => Some(Kind::Str),
+        _ => None,
+    }
+}
+
+/// Repeatedly removes or simplifies parts of `program` while `fails` still holds,
+/// returning the smallest failing program found.
+///
+/// Loop conditions are never touched, but a shrunk loop body may no longer be
+/// balanced, so `fails` should run programs with a step limit.
+pub fn shrink<F>(program: Vec<Stmt>, mut fails: F) -> Vec<Stmt>
+where
+    F: FnMut(&[Stmt]) -> bool,
+{
+    let mut current = program;
+    'shrink: loop {
+        for candidate in candidates(&current) {
+            if fails(&candidate) {
+                current = candidate;
+                continue 'shrink;
+            }
+        }
+        return current;
+    }
+}
+
+fn candidates(stmts: &[Stmt]) -> Vec<Vec<Stmt>> {
+    let mut out = vec![];
+    for (index, stmt) in stmts.iter().enumerate() {
+        let mut removed = stmts.to_vec();
+        removed.remove(index);
+        out.push(removed);
+
+        for replacement in simplify(stmt) {
+            let mut replaced = stmts.to_vec();
+            replaced.splice(index..=index, replacement);
+            out.push(replaced);
+        }
+    }
+    out
+}
+
+fn simplify(stmt: &Stmt) -> Vec<Vec<Stmt>> {
+    match stmt {
+        Stmt::Literal(value) => {
+            let simplest = match value {
+                Value::Int(_) | Value::BigInt(_) => Value::Int(0),
+                Value::Float(_) => Value::Float(0.0),
+                Value::Str(_) => Value::from(""),
+                Value::List(_) => Value::list(vec![]),
+                Value::Map(_) => Value::map(Map::default()),
+            };
+            if simplest == *value {
+                vec![]
+            } else {
+                vec![vec![Stmt::Literal(simplest)]]
+            }
+        }
+        Stmt::If(body) => {
+            let mut out = vec![body.clone()];
+            out.extend(
+                candidates(body)
+                    .into_iter()
+                    .map(|body| vec![Stmt::If(body)]),
+            );
+            out
+        }
+        Stmt::IfElse(body, or_else) => {
+            let mut out = vec![body.clone(), or_else.clone()];
+            out.extend(
+                candidates(body)
+                    .into_iter()
+                    .map(|body| vec![Stmt::IfElse(body, or_else.clone())]),
+            );
+            out.extend(
+                candidates(or_else)
+                    .into_iter()
+                    .map(|or_else| vec![Stmt::IfElse(body.clone(), or_else)]),
+            );
+            out
+        }
+        Stmt::While(condition, body) => {
+            // Generated loops leave their exhausted counter behind.
+            let mut out = vec![vec![Stmt::Literal(Value::Int(0))]];
+            out.extend(
+                candidates(body)
+                    .into_iter()
+                    .map(|body| vec![Stmt::While(condition.clone(), body)]),
+            );
+            out
+        }
+        Stmt::BinOp(_) | Stmt::Dup | Stmt::Nop => vec![],
+    }
+}
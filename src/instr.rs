@@ -0,0 +1,193 @@
+use crate::{BinOp, Error, UnaryOp, Value};
+use std::fmt;
+
+/// The index of the instruction a jump lands on, equal to the number
+/// of instructions when jumping to the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target(pub usize);
+
+/// A decoded instruction, shared by both stack encodings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instr {
+    Nop,
+    Dup,
+    /// Discards the top value.
+    Pop,
+    /// Exchanges the top two values.
+    Swap,
+    /// Moves the top value below the next two, turning `a b c` into `c a b`.
+    Rot3,
+    /// Pushes a copy of the value below the top.
+    Over,
+    /// Discards that many values.
+    PopN(u32),
+    /// Pops a value and writes it to the machine's output on its own line.
+    Print,
+    /// Pops a value and pushes its `Display` as a string.
+    ToStr,
+    /// Pops a value and pushes it as an int, parsing strings.
+    ToInt,
+    /// Pops a value and pushes it as a float, parsing strings.
+    ToFloat,
+    /// Pops a value for each placeholder of the template string constant at
+    /// that index and pushes the template with them filled in.
+    Format(u32),
+    BinOp(BinOp),
+    UnaryOp(UnaryOp),
+    LoadConst(u32),
+    Jump(Target),
+    PopJumpIfFalse(Target),
+    PopJumpIfTrue(Target),
+    /// Jumps if the top is false and pops it otherwise, for short-circuit `and`.
+    JumpIfFalseOrPop(Target),
+    /// Jumps if the top is true and pops it otherwise, for short-circuit `or`.
+    JumpIfTrueOrPop(Target),
+    /// Pops that many values and pushes a list of them in push order.
+    BuildList(u32),
+    /// Pops an index and a list and pushes the item at that index.
+    Index,
+    /// Pops a value, an index and a list and stores the value at that index.
+    StoreIndex,
+    /// Pops a list or a map and pushes its length.
+    Len,
+    /// Pops a value and appends it to the list below, which stays on the stack.
+    Append,
+    /// Pops that many key and value pairs and pushes a map of them.
+    BuildMap(u32),
+    /// Pops a key and a map and pushes the value stored under that key.
+    GetItem,
+    /// Pops a value, a key and a map and stores the value under that key.
+    SetItem,
+    /// Pops a key and a map and pushes whether the map holds that key.
+    HasKey,
+    /// Pops a map and pushes a list of its keys in insertion order.
+    Keys,
+}
+
+impl Instr {
+    /// Where the instruction jumps to, if it is a jump.
+    #[must_use]
+    pub fn target(&self) -> Option<Target> {
+        match *self {
+            Self::Jump(target)
+            | Self::PopJumpIfFalse(target)
+            | Self::PopJumpIfTrue(target)
+            | Self::JumpIfFalseOrPop(target)
+            | Self::JumpIfTrueOrPop(target) => Some(target),
+            _ => None,
+        }
+    }
+    pub fn target_mut(&mut self) -> Option<&mut Target> {
+        match self {
+            Self::Jump(target)
+            | Self::PopJumpIfFalse(target)
+            | Self::PopJumpIfTrue(target)
+            | Self::JumpIfFalseOrPop(target)
+            | Self::JumpIfTrueOrPop(target) => Some(target),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the byte offset of every instruction followed by the total length.
+pub fn layout<F>(instrs: &[Instr], mut size: F) -> Vec<usize>
+where
+    F: FnMut(&Instr) -> usize,
+{
+    let mut offsets = Vec::with_capacity(instrs.len() + 1);
+    let mut offset = 0;
+    offsets.push(offset);
+    for instr in instrs {
+        offset += size(instr);
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// The operand a relative jump ending at `end` stores to land on `target`.
+#[must_use]
+pub fn relative(end: usize, target: usize) -> isize {
+    isize::try_from(target).unwrap() - isize::try_from(end).unwrap()
+}
+
+/// The byte offset a relative jump ending at `end` lands on.
+pub fn absolute(end: usize, offset: isize) -> Result<usize, Error> {
+    end.checked_add_signed(offset)
+        .ok_or(Error::InvalidJump(end))
+}
+
+/// Converts the byte offsets of decoded jumps into `Target`s.
+///
+/// `offsets` holds the start of every instruction followed by the total length.
+pub fn resolve_targets(decoded: &mut [Instr], offsets: &[usize]) -> Result<(), Error> {
+    for instr in decoded {
+        if let Some(target) = instr.target_mut() {
+            let offset = target.0;
+            // Jumping past the end finishes execution, just like jumping to the end.
+            let index = offsets
+                .binary_search(&offset.min(*offsets.last().unwrap()))
+                .map_err(|_| Error::InvalidJump(offset))?;
+            *target = Target(index);
+        }
+    }
+    Ok(())
+}
+
+/// Writes one line per instruction, prefixed with its byte offset.
+pub fn disassemble(
+    f: &mut fmt::Formatter<'_>,
+    instrs: &[Instr],
+    offsets: &[usize],
+    constants: &[Value],
+) -> fmt::Result {
+    for (instr, offset) in instrs.iter().zip(offsets) {
+        write!(f, "{offset} ")?;
+        match instr {
+            Instr::Nop => writeln!(f, "Nop")?,
+            Instr::Dup => writeln!(f, "Dup")?,
+            Instr::Pop => writeln!(f, "Pop")?,
+            Instr::Swap => writeln!(f, "Swap")?,
+            Instr::Rot3 => writeln!(f, "Rot3")?,
+            Instr::Over => writeln!(f, "Over")?,
+            Instr::PopN(len) => writeln!(f, "PopN ({len})")?,
+            Instr::Print => writeln!(f, "Print")?,
+            Instr::ToStr => writeln!(f, "ToStr")?,
+            Instr::ToInt => writeln!(f, "ToInt")?,
+            Instr::ToFloat => writeln!(f, "ToFloat")?,
+            Instr::Format(index) => match constants.get(*index as usize) {
+                Some(value) => writeln!(f, "Format ({index}) ({value:?})")?,
+                None => writeln!(f, "Format ({index}) (missing)")?,
+            },
+            Instr::BinOp(binop) => writeln!(f, "BinOp ({binop:?})")?,
+            Instr::UnaryOp(op) => writeln!(f, "UnaryOp ({op:?})")?,
+            Instr::LoadConst(index) => match constants.get(*index as usize) {
+                Some(value) => writeln!(f, "LoadConst ({index}) ({value:?})")?,
+                None => writeln!(f, "LoadConst ({index}) (missing)")?,
+            },
+            Instr::Jump(target) => writeln!(f, "Jump ({})", offsets[target.0])?,
+            Instr::PopJumpIfFalse(target) => {
+                writeln!(f, "PopJumpIfFalse ({})", offsets[target.0])?;
+            }
+            Instr::PopJumpIfTrue(target) => {
+                writeln!(f, "PopJumpIfTrue ({})", offsets[target.0])?;
+            }
+            Instr::JumpIfFalseOrPop(target) => {
+                writeln!(f, "JumpIfFalseOrPop ({})", offsets[target.0])?;
+            }
+            Instr::JumpIfTrueOrPop(target) => {
+                writeln!(f, "JumpIfTrueOrPop ({})", offsets[target.0])?;
+            }
+            Instr::BuildList(len) => writeln!(f, "BuildList ({len})")?,
+            Instr::Index => writeln!(f, "Index")?,
+            Instr::StoreIndex => writeln!(f, "StoreIndex")?,
+            Instr::Len => writeln!(f, "Len")?,
+            Instr::Append => writeln!(f, "Append")?,
+            Instr::BuildMap(len) => writeln!(f, "BuildMap ({len})")?,
+            Instr::GetItem => writeln!(f, "GetItem")?,
+            Instr::SetItem => writeln!(f, "SetItem")?,
+            Instr::HasKey => writeln!(f, "HasKey")?,
+            Instr::Keys => writeln!(f, "Keys")?,
+        }
+    }
+    Ok(())
+}
@@ -1,11 +1,34 @@
 #![warn(clippy::pedantic)]
-#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::missing_panics_doc, clippy::missing_errors_doc)]
 
+pub mod register;
+pub mod threaded;
 pub mod two_byte;
 pub mod variable_length;
 
+pub mod arithmetic;
+pub mod assembler;
+pub mod bigint;
 pub mod binop;
+pub mod constants;
+pub mod differential;
+pub mod error;
+pub mod format;
+pub mod generator;
+pub mod instr;
+pub mod map;
+pub mod output;
+pub mod packed;
+pub mod program;
+pub mod unaryop;
 pub mod value;
 
+pub use arithmetic::ArithmeticMode;
+pub use assembler::{Assembler, Machine};
 pub use binop::BinOp;
-pub use value::Value;
+pub use error::Error;
+pub use unaryop::UnaryOp;
+pub use value::{Slot, Value};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,123 @@
+use crate::{bigint::BigInt, Error, Value};
+use std::{cmp::Ordering, collections::HashMap, fmt, rc::Rc};
+
+/// The values that can key a map.
+///
+/// Floats are rejected rather than keyed by their bits, since `0.0 == -0.0`
+/// and NaN never equals itself. Comparisons produce ints, so booleans key as
+/// `0` and `1`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Int(i64),
+    BigInt(Rc<BigInt>),
+    Str(Rc<str>),
+}
+
+impl TryFrom<&Value> for Key {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Int(int) => Ok(Self::Int(*int)),
+            Value::BigInt(big) => Ok(Self::BigInt(big.clone())),
+            Value::Str(str) => Ok(Self::Str(str.clone())),
+            _ => Err(Error::UnhashableKey(value.type_name())),
+        }
+    }
+}
+
+impl From<Key> for Value {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::Int(int) => Self::Int(int),
+            Key::BigInt(big) => Self::BigInt(big),
+            Key::Str(str) => Self::Str(str),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(int) => write!(f, "{int}"),
+            Self::BigInt(big) => write!(f, "{big}"),
+            Self::Str(str) => write!(f, "{str:?}"),
+        }
+    }
+}
+
+/// A map that iterates in insertion order, so that `Keys` doesn't depend on hashing.
+///
+/// Overwriting a key keeps its original position.
+#[derive(Clone, Default)]
+pub struct Map {
+    entries: Vec<(Key, Value)>,
+    indices: HashMap<Key, usize>,
+}
+
+impl Map {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    #[must_use]
+    pub fn get(&self, key: &Key) -> Option<&Value> {
+        self.indices.get(key).map(|&index| &self.entries[index].1)
+    }
+    #[must_use]
+    pub fn contains_key(&self, key: &Key) -> bool {
+        self.indices.contains_key(key)
+    }
+    /// Returns the value previously stored under `key`, if any.
+    pub fn insert(&mut self, key: Key, value: Value) -> Option<Value> {
+        if let Some(&index) = self.indices.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[index].1, value));
+        }
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&Key, &Value)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+impl FromIterator<(Key, Value)> for Map {
+    fn from_iter<I: IntoIterator<Item = (Key, Value)>>(entries: I) -> Self {
+        let mut map = Self::default();
+        for (key, value) in entries {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+/// Maps are equal when they hold the same entries, whatever order they were inserted in.
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+/// Maps are unordered, this only exists so that `Value` can derive `PartialOrd`.
+impl PartialOrd for Map {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self == other).then_some(Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{Error, Value};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, Write},
+    rc::Rc,
+};
+
+/// Where `Print` writes, stdout unless the host swaps in its own writer.
+pub struct Output(Box<dyn Write>);
+
+impl Output {
+    pub fn new(writer: impl Write + 'static) -> Self {
+        Self(Box::new(writer))
+    }
+    /// Writes `value` followed by a newline.
+    pub fn print(&mut self, value: &Value) -> Result<(), Error> {
+        writeln!(self, "{value}").map_err(|err| Error::Output(err.kind()))
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Output")
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// An in-memory writer whose clones share the same bytes, so that a host can
+/// hand one to a machine and read what was printed through another.
+#[derive(Debug, Clone, Default)]
+pub struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Buffer {
+    #[must_use]
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{value::Slot, ArithmeticMode, BinOp, Error, Value};
+use std::{fmt, marker::PhantomData, mem::ManuallyDrop, ptr, rc::Rc};
+
+/// Quiet NaNs with the two top mantissa bits set are never produced by
+/// arithmetic, which leaves their 50 low bits free for a payload. The sign
+/// bit tells inline ints from pointers.
+const TAG_MASK: u64 = 0xFFFC_0000_0000_0000;
+const INT_TAG: u64 = 0x7FFC_0000_0000_0000;
+const PTR_TAG: u64 = 0xFFFC_0000_0000_0000;
+const PAYLOAD_BITS: u32 = 50;
+const PAYLOAD_MASK: u64 = (1 << PAYLOAD_BITS) - 1;
+
+/// A `Value` NaN-boxed into 8 bytes.
+///
+/// Floats are stored as themselves and ints that fit in 50 bits are stored in
+/// the payload of a tagged NaN. Everything else, including the odd float whose
+/// bits collide with a tag, lives behind a tagged `Rc<Value>`.
+///
+/// The pointer has to fit in the payload too, which holds the 47 and 48 bit
+/// user space addresses of current 64-bit targets. `Slot::from_value` checks
+/// this for every boxed value and panics on an address it would truncate.
+pub struct Packed {
+    bits: u64,
+    _rc: PhantomData<Rc<Value>>,
+}
+
+/// A borrowed view of what a `Packed` holds.
+enum Unpacked<'p> {
+    Int(i64),
+    Float(f64),
+    Heap(&'p Value),
+}
+
+impl Packed {
+    const fn from_bits(bits: u64) -> Self {
+        Self {
+            bits,
+            _rc: PhantomData,
+        }
+    }
+    fn int(int: i64) -> Option<Self> {
+        let payload = u64::from_ne_bytes(int.to_ne_bytes()) & PAYLOAD_MASK;
+        if Self::payload_int(payload) == int {
+            Some(Self::from_bits(INT_TAG | payload))
+        } else {
+            Self::boxed(Value::Int(int))
+        }
+    }
+    fn float(float: f64) -> Option<Self> {
+        let bits = float.to_bits();
+        if bits & INT_TAG == INT_TAG {
+            Self::boxed(Value::Float(float))
+        } else {
+            Some(Self::from_bits(bits))
+        }
+    }
+    /// Returns `None` if the address of the allocation doesn't fit the payload.
+    fn boxed(value: Value) -> Option<Self> {
+        let ptr = Rc::into_raw(Rc::new(value));
+        let addr = ptr.expose_provenance() as u64;
+        if addr & !PAYLOAD_MASK == 0 {
+            Some(Self::from_bits(PTR_TAG | addr))
+        } else {
+            // SAFETY: takes back the count that was just leaked by `into_raw`.
+            drop(unsafe { Rc::from_raw(ptr) });
+            None
+        }
+    }
+    /// Sign extends the low `PAYLOAD_BITS` of `payload`.
+    fn payload_int(payload: u64) -> i64 {
+        let shift = u64::BITS - PAYLOAD_BITS;
+        i64::from_ne_bytes((payload << shift).to_ne_bytes()) >> shift
+    }
+    fn ptr(&self) -> *const Value {
+        debug_assert_eq!(self.bits & TAG_MASK, PTR_TAG);
+        ptr::with_exposed_provenance(usize::try_from(self.bits & PAYLOAD_MASK).unwrap())
+    }
+    fn unpack(&self) -> Unpacked<'_> {
+        match self.bits & TAG_MASK {
+            INT_TAG => Unpacked::Int(Self::payload_int(self.bits & PAYLOAD_MASK)),
+            // SAFETY: pointer tags are only created by `boxed`, and the count
+            // it owns keeps the value alive for as long as `self`.
+            PTR_TAG => Unpacked::Heap(unsafe { &*self.ptr() }),
+            _ => Unpacked::Float(f64::from_bits(self.bits)),
+        }
+    }
+}
+
+impl Slot for Packed {
+    /// # Panics
+    ///
+    /// If the value has to be boxed and its address doesn't fit in 50 bits.
+    fn from_value(value: Value) -> Self {
+        let packed = match value {
+            Value::Int(int) => Self::int(int),
+            Value::Float(float) => Self::float(float),
+            value @ (Value::BigInt(_) | Value::Str(_) | Value::List(_) | Value::Map(_)) => {
+                Self::boxed(value)
+            }
+        };
+        packed.expect("heap address doesn't fit the NaN-box payload")
+    }
+    fn into_value(self) -> Value {
+        let this = ManuallyDrop::new(self);
+        match this.unpack() {
+            Unpacked::Int(int) => Value::Int(int),
+            Unpacked::Float(float) => Value::Float(float),
+            Unpacked::Heap(_) => {
+                // SAFETY: takes over the count owned by `this`, which is not dropped.
+                let rc = unsafe { Rc::from_raw(this.ptr()) };
+                Rc::try_unwrap(rc).unwrap_or_else(|rc| (*rc).clone())
+            }
+        }
+    }
+    /// Inline numbers unpack without allocating, so everything goes through `Value`.
+    fn run_binop(lhs: Self, rhs: Self, op: BinOp, mode: ArithmeticMode) -> Result<Self, Error> {
+        Value::run_binop(lhs.into_value(), rhs.into_value(), op, mode).map(Self::from_value)
+    }
+    /// Only lists and maps need copying, everything else stays shared.
+    fn load_const(constant: &Self) -> Self {
+        match constant.unpack() {
+            Unpacked::Heap(value @ (Value::List(_) | Value::Map(_))) => {
+                Self::from_value(value.deep_copy())
+            }
+            _ => constant.clone(),
+        }
+    }
+    fn is_truthy(&self) -> bool {
+        match self.unpack() {
+            Unpacked::Int(int) => int != 0,
+            Unpacked::Float(float) => float != 0.0,
+            Unpacked::Heap(value) => value.is_truthy(),
+        }
+    }
+}
+
+impl Clone for Packed {
+    fn clone(&self) -> Self {
+        if self.bits & TAG_MASK == PTR_TAG {
+            // SAFETY: `self` owns a count, so the allocation is alive.
+            unsafe { Rc::increment_strong_count(self.ptr()) };
+        }
+        Self::from_bits(self.bits)
+    }
+}
+
+impl Drop for Packed {
+    fn drop(&mut self) {
+        if self.bits & TAG_MASK == PTR_TAG {
+            // SAFETY: releases the count owned by `self`.
+            unsafe { drop(Rc::from_raw(self.ptr())) };
+        }
+    }
+}
+
+impl fmt::Debug for Packed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unpack() {
+            Unpacked::Int(int) => Value::Int(int).fmt(f),
+            Unpacked::Float(float) => Value::Float(float).fmt(f),
+            Unpacked::Heap(value) => value.fmt(f),
+        }
+    }
+}
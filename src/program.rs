@@ -0,0 +1,50 @@
+use crate::{Assembler, BinOp, Value};
+
+/// An encoding independent program that can be lowered into any `Assembler`.
+///
+/// Control flow is structured, so every lowered jump lands on an instruction boundary.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Literal(Value),
+    BinOp(BinOp),
+    Dup,
+    Nop,
+    If(Vec<Stmt>),
+    IfElse(Vec<Stmt>, Vec<Stmt>),
+    While(Vec<Stmt>, Vec<Stmt>),
+}
+
+impl Stmt {
+    pub fn lower<A: Assembler>(&self, pool: &mut A) {
+        match self {
+            Self::Literal(value) => {
+                pool.push_const(value.clone());
+            }
+            Self::BinOp(binop) => pool.push_binop(*binop),
+            Self::Dup => pool.push_dup(),
+            Self::Nop => pool.push_nop(),
+            Self::If(body) => pool.push_if(|pool| lower_all(body, pool)),
+            Self::IfElse(body, or_else) => pool.push_if_or_else(
+                |pool| lower_all(body, pool),
+                |pool| lower_all(or_else, pool),
+            ),
+            Self::While(condition, body) => pool.push_while_loop(
+                |pool| lower_all(condition, pool),
+                |pool| lower_all(body, pool),
+            ),
+        }
+    }
+}
+
+pub fn lower_all<A: Assembler>(stmts: &[Stmt], pool: &mut A) {
+    for stmt in stmts {
+        stmt.lower(pool);
+    }
+}
+
+#[must_use]
+pub fn lower<A: Assembler>(stmts: &[Stmt]) -> A {
+    let mut pool = A::default();
+    lower_all(stmts, &mut pool);
+    pool
+}
@@ -0,0 +1,157 @@
+use crate::{constants::Constants, BinOp, Value};
+use std::{fmt, ops::Deref};
+
+/// Every instruction is 4 bytes: the opcode followed by three operand bytes.
+///
+/// Binary operations don't have a variant of their own, instead
+/// `OpCode::BinOp as u8 + binop as u8` encodes `binop dst, lhs, rhs`.
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum OpCode {
+    NOP = 0,
+
+    Move,
+    LoadConst,
+
+    Jump,
+    JumpIfFalse,
+
+    BinOp,
+}
+
+impl OpCode {
+    pub const INSTRUCTION_SIZE: usize = 4;
+    pub const BINOP_COUNT: u8 = BinOp::COUNT;
+}
+
+#[derive(Debug, Default)]
+pub struct Pool {
+    pub bytes: Vec<u8>,
+    pub constants: Constants,
+    pub register_count: usize,
+}
+
+impl Pool {
+    pub fn push(&mut self, op_byte: u8, operands: [u8; 3]) {
+        self.bytes.push(op_byte);
+        self.bytes.extend_from_slice(&operands);
+    }
+    pub fn push_nop(&mut self) {
+        self.push(OpCode::NOP as u8, [0; 3]);
+    }
+    pub fn push_move(&mut self, dst: u8, src: u8) {
+        self.use_registers(&[dst, src]);
+        self.push(OpCode::Move as u8, [dst, src, 0]);
+    }
+    pub fn push_const(&mut self, dst: u8, val: Value) {
+        self.use_registers(&[dst]);
+        let [low, high] = self.insert_const(val).to_le_bytes();
+        self.push(OpCode::LoadConst as u8, [dst, low, high]);
+    }
+    #[inline]
+    pub fn push_literal<V: Into<Value>>(&mut self, dst: u8, val: V) {
+        self.push_const(dst, val.into());
+    }
+    pub fn push_binop(&mut self, binop: BinOp, dst: u8, lhs: u8, rhs: u8) {
+        self.use_registers(&[dst, lhs, rhs]);
+        self.push(OpCode::BinOp as u8 + binop as u8, [dst, lhs, rhs]);
+    }
+    pub fn insert_const(&mut self, val: Value) -> u16 {
+        u16::try_from(self.constants.insert(val)).unwrap()
+    }
+    pub fn push_jump(&mut self, pos: u16) -> usize {
+        let [low, high] = pos.to_le_bytes();
+        self.push(OpCode::Jump as u8, [0, low, high]);
+        self.len() - OpCode::INSTRUCTION_SIZE
+    }
+    pub fn push_jump_if_false(&mut self, condition: u8, pos: u16) -> usize {
+        self.use_registers(&[condition]);
+        let [low, high] = pos.to_le_bytes();
+        self.push(OpCode::JumpIfFalse as u8, [condition, low, high]);
+        self.len() - OpCode::INSTRUCTION_SIZE
+    }
+    pub fn patch_jump(&mut self, pos: usize) {
+        let [low, high] = self.len_u16().to_le_bytes();
+        self.bytes[pos + 2] = low;
+        self.bytes[pos + 3] = high;
+    }
+    #[must_use]
+    pub fn len_u16(&self) -> u16 {
+        u16::try_from(self.len()).unwrap()
+    }
+    pub fn push_if<F>(&mut self, condition: u8, body: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let jump = self.push_jump_if_false(condition, 0);
+        body(self);
+        self.patch_jump(jump);
+    }
+    pub fn push_if_or_else<F1, F2>(&mut self, condition: u8, body: F1, or_else: F2)
+    where
+        F1: FnOnce(&mut Self),
+        F2: FnOnce(&mut Self),
+    {
+        let jump_if = self.push_jump_if_false(condition, 0);
+        body(self);
+        let jump_else = self.push_jump(0);
+        self.patch_jump(jump_if);
+        or_else(self);
+        self.patch_jump(jump_else);
+    }
+    /// `condition` emits code computing the condition and returns the register holding it.
+    pub fn push_while_loop<F1, F2>(&mut self, condition: F1, body: F2)
+    where
+        F1: FnOnce(&mut Self) -> u8,
+        F2: FnOnce(&mut Self),
+    {
+        let start = self.len_u16();
+        let condition = condition(self);
+        let jump = self.push_jump_if_false(condition, 0);
+        body(self);
+        self.push_jump(start);
+        self.patch_jump(jump);
+    }
+    fn use_registers(&mut self, registers: &[u8]) {
+        for &register in registers {
+            self.register_count = self.register_count.max(register as usize + 1);
+        }
+    }
+}
+
+impl Deref for Pool {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.bytes
+    }
+}
+
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, instruction) in self.chunks_exact(OpCode::INSTRUCTION_SIZE).enumerate() {
+            let head = index * OpCode::INSTRUCTION_SIZE;
+            let [op_byte, a, b, c] = instruction.try_into().unwrap();
+            let wide = u16::from_le_bytes([b, c]);
+
+            write!(f, "{head} ")?;
+            if op_byte >= OpCode::BinOp as u8 {
+                let binop = BinOp::try_from(op_byte - OpCode::BinOp as u8).unwrap();
+                writeln!(f, "{binop:?} r{a}, r{b}, r{c}")?;
+                continue;
+            }
+            let op: OpCode = unsafe { std::mem::transmute(op_byte) };
+            match op {
+                OpCode::NOP => writeln!(f, "Nop")?,
+                OpCode::Move => writeln!(f, "Move r{a}, r{b}")?,
+                OpCode::LoadConst => {
+                    let constant = &self.constants[wide as usize];
+                    writeln!(f, "LoadConst r{a}, ({constant:?})")?;
+                }
+                OpCode::Jump => writeln!(f, "Jump ({wide})")?,
+                OpCode::JumpIfFalse => writeln!(f, "JumpIfFalse r{a}, ({wide})")?,
+                OpCode::BinOp => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod bytecode;
+pub mod vm;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,183 @@
+use super::{bytecode::Pool, vm};
+use crate::{BinOp, Error, Value};
+
+mod load_literals {
+    use super::*;
+
+    #[test]
+    fn consts() {
+        let mut pool = Pool::default();
+        pool.push_literal(0, 1);
+        pool.push_literal(1, 1.5);
+        pool.push_literal(2, "Hello, World!");
+
+        eprintln!("{pool}");
+        let registers = vm::create_and_run(&pool).unwrap();
+        assert_eq!(
+            registers,
+            vec![
+                Value::Int(1),
+                Value::Float(1.5),
+                Value::Str("Hello, World!".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn dedup() {
+        let mut pool = Pool::default();
+        pool.push_literal(0, 1);
+        pool.push_literal(1, 1);
+
+        assert_eq!(*pool.constants, [Value::Int(1)]);
+    }
+
+    #[test]
+    fn moves() {
+        let mut pool = Pool::default();
+        pool.push_literal(0, 2.0);
+        pool.push_move(1, 0);
+
+        let registers = vm::create_and_run(&pool).unwrap();
+        assert_eq!(registers, vec![Value::Float(2.0), Value::Float(2.0)]);
+    }
+}
+
+mod binops {
+    use super::*;
+
+    fn run_binop(lhs: Value, rhs: Value, op: BinOp) -> Result<(), Error> {
+        let mut pool = Pool::default();
+        pool.push_const(0, lhs);
+        pool.push_const(1, rhs);
+        pool.push_binop(op, 2, 0, 1);
+
+        vm::create_and_run(&pool).map(drop)
+    }
+
+    #[test]
+    fn add() {
+        let mut pool = Pool::default();
+        pool.push_literal(0, 1);
+        pool.push_literal(1, 0.5);
+        pool.push_binop(BinOp::Add, 0, 0, 1);
+        pool.push_literal(2, "Hello, ");
+        pool.push_literal(3, "World!");
+        pool.push_binop(BinOp::Add, 2, 2, 3);
+
+        eprintln!("{pool}");
+        let registers = vm::create_and_run(&pool).unwrap();
+        assert_eq!(registers[0], Value::Float(1.5));
+        assert_eq!(registers[2], Value::Str("Hello, World!".into()));
+    }
+
+    #[test]
+    fn sub_mul() {
+        let mut pool = Pool::default();
+        pool.push_literal(0, 2);
+        pool.push_literal(1, 3);
+        pool.push_binop(BinOp::Add, 2, 0, 1);
+        pool.push_binop(BinOp::Sub, 2, 2, 0);
+        pool.push_literal(3, "Hello, ");
+        pool.push_binop(BinOp::Mul, 3, 2, 3);
+
+        let registers = vm::create_and_run(&pool).unwrap();
+        assert_eq!(registers[2], Value::Int(3));
+        assert_eq!(registers[3], Value::Str("Hello, Hello, Hello, ".into()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            run_binop("a".into(), Value::Int(1), BinOp::Sub),
+            Err(Error::UnsupportedOperands {
+                op: BinOp::Sub,
+                lhs: "str",
+                rhs: "int"
+            })
+        );
+        assert_eq!(
+            run_binop(Value::Int(1), Value::Int(0), BinOp::Div),
+            Err(Error::DivisionByZero)
+        );
+    }
+}
+
+mod jumps {
+    use super::*;
+
+    #[test]
+    fn patch_jump() {
+        let mut pool = Pool::default();
+        pool.push_literal(0, 1);
+        let jump = pool.push_jump(0);
+        pool.push_literal(0, 2);
+        pool.patch_jump(jump);
+        pool.push_literal(1, 4);
+
+        eprintln!("{pool}");
+        let registers = vm::create_and_run(&pool).unwrap();
+        assert_eq!(registers, vec![Value::Int(1), Value::Int(4)]);
+    }
+
+    #[test]
+    fn if_or_else() {
+        for (condition, expected) in [(1, "if"), (0, "else")] {
+            let mut pool = Pool::default();
+            pool.push_literal(0, condition);
+            pool.push_if_or_else(
+                0,
+                |if_body| if_body.push_literal(1, "if"),
+                |else_body| else_body.push_literal(1, "else"),
+            );
+
+            let registers = vm::create_and_run(&pool).unwrap();
+            assert_eq!(registers[1], Value::Str(expected.into()));
+        }
+    }
+
+    #[test]
+    fn if_false() {
+        let mut pool = Pool::default();
+        pool.push_literal(0, 0);
+        pool.push_literal(1, ", World!");
+        pool.push_if(0, |if_body| if_body.push_literal(1, "Hello"));
+
+        let registers = vm::create_and_run(&pool).unwrap();
+        assert_eq!(registers[1], Value::Str(", World!".into()));
+    }
+
+    #[test]
+    fn while_loop() {
+        let mut pool = Pool::default();
+        pool.push_literal(0, 4);
+        pool.push_literal(1, 0);
+        pool.push_literal(2, 1);
+        pool.push_literal(3, 0);
+
+        pool.push_while_loop(
+            |condition| {
+                condition.push_binop(BinOp::Sub, 0, 0, 2);
+                condition.push_binop(BinOp::GT, 4, 0, 3);
+                4
+            },
+            |body| {
+                body.push_binop(BinOp::Add, 1, 1, 0);
+            },
+        );
+
+        eprintln!("{pool}");
+        let registers = vm::create_and_run(&pool).unwrap();
+        assert_eq!(registers[0], Value::Int(0));
+        assert_eq!(registers[1], Value::Int(3 + 2 + 1));
+    }
+}
+
+#[test]
+fn invalid_register() {
+    let mut pool = Pool::default();
+    pool.push_literal(0, 1);
+    pool.bytes[1] = 1;
+
+    assert_eq!(vm::create_and_run(&pool), Err(Error::InvalidRegister(1)));
+}
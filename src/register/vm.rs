@@ -0,0 +1,93 @@
+use super::bytecode::{OpCode, Pool};
+use crate::{ArithmeticMode, BinOp, Error, Value};
+use std::rc::Rc;
+
+pub fn create_and_run(pool: &Pool) -> Result<Vec<Value>, Error> {
+    let mut vm = Vm::new(pool);
+    vm.run()?;
+    Ok(vm.registers)
+}
+
+#[derive(Debug)]
+pub struct Vm {
+    pub bytes: Rc<[u8]>,
+    pub constants: Rc<[Value]>,
+    pub head: usize,
+    pub registers: Vec<Value>,
+    pub mode: ArithmeticMode,
+}
+
+impl Vm {
+    #[must_use]
+    pub fn new(pool: &Pool) -> Self {
+        Self {
+            bytes: pool.bytes.as_slice().into(),
+            constants: (*pool.constants).into(),
+            head: 0,
+            registers: vec![Value::Int(0); pool.register_count],
+            mode: ArithmeticMode::default(),
+        }
+    }
+    pub fn run(&mut self) -> Result<(), Error> {
+        while self.head < self.bytes.len() {
+            self.run_next()?;
+        }
+        Ok(())
+    }
+    pub fn run_next(&mut self) -> Result<(), Error> {
+        let [op_byte, a, b, c] = self.read();
+        self.head += OpCode::INSTRUCTION_SIZE;
+
+        if op_byte >= OpCode::BinOp as u8 {
+            let binop = BinOp::try_from(op_byte - OpCode::BinOp as u8)
+                .map_err(|_| Error::InvalidOpCode(op_byte))?;
+
+            let lhs = self.register(b)?.clone();
+            let rhs = self.register(c)?.clone();
+            *self.register_mut(a)? = Value::run_binop(lhs, rhs, binop, self.mode)?;
+            return Ok(());
+        }
+        let op_code: OpCode = unsafe { std::mem::transmute(op_byte) };
+        match op_code {
+            OpCode::NOP => (),
+            OpCode::Move => {
+                let value = self.register(b)?.clone();
+                *self.register_mut(a)? = value;
+            }
+            OpCode::LoadConst => {
+                let index = u16::from_le_bytes([b, c]) as usize;
+                let constant = self
+                    .constants
+                    .get(index)
+                    .ok_or(Error::InvalidConstant(index))?;
+                *self.register_mut(a)? = constant.clone();
+            }
+            OpCode::Jump => {
+                self.head = u16::from_le_bytes([b, c]) as usize;
+            }
+            OpCode::JumpIfFalse => {
+                if !self.register(a)?.is_truthy() {
+                    self.head = u16::from_le_bytes([b, c]) as usize;
+                }
+            }
+            OpCode::BinOp => unreachable!(),
+        }
+        Ok(())
+    }
+    pub fn register(&self, register: u8) -> Result<&Value, Error> {
+        self.registers
+            .get(register as usize)
+            .ok_or(Error::InvalidRegister(register))
+    }
+    pub fn register_mut(&mut self, register: u8) -> Result<&mut Value, Error> {
+        self.registers
+            .get_mut(register as usize)
+            .ok_or(Error::InvalidRegister(register))
+    }
+    #[must_use]
+    pub fn read(&self) -> [u8; OpCode::INSTRUCTION_SIZE] {
+        self.bytes[self.head..self.head + OpCode::INSTRUCTION_SIZE]
+            .try_into()
+            .unwrap()
+    }
+}
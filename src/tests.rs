@@ -0,0 +1,1036 @@
+use crate::{
+    map::{Key, Map},
+    Assembler, BinOp, Error, Machine, Value,
+};
+
+fn count_down<A: Assembler>(pool: &mut A) {
+    pool.push_literal(4);
+    pool.push_while_loop(
+        |condition| {
+            condition.push_literal(1);
+            condition.push_binop(BinOp::Sub);
+            condition.push_dup();
+        },
+        |body| {
+            body.push_dup();
+        },
+    );
+}
+
+fn if_else<A: Assembler>(pool: &mut A, condition: i64) {
+    pool.push_literal(condition);
+    pool.push_if_or_else(
+        |if_body| {
+            if_body.push_literal("if");
+        },
+        |else_body| {
+            else_body.push_literal("else");
+        },
+    );
+}
+
+fn check_count_down<M: Machine>() {
+    let mut pool = M::Pool::default();
+    count_down(&mut pool);
+
+    eprintln!("{pool}");
+    let stack = M::create_and_run(&pool).unwrap();
+    assert_eq!(
+        stack,
+        vec![Value::Int(3), Value::Int(2), Value::Int(1), Value::Int(0)]
+    );
+}
+
+fn check_if_else<M: Machine>() {
+    for (condition, expected) in [(1, "if"), (0, "else")] {
+        let mut pool = M::Pool::default();
+        if_else(&mut pool, condition);
+
+        let stack = M::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Str(expected.into())]);
+    }
+}
+
+fn check_nop<M: Machine>() {
+    let mut pool = M::Pool::default();
+    pool.push_nop();
+    pool.push_literal(1.5);
+    pool.push_nop();
+
+    let stack = M::create_and_run(&pool).unwrap();
+    assert_eq!(stack, vec![Value::Float(1.5)]);
+}
+
+fn check_short_circuit<M: Machine>() {
+    // Dividing by zero shows whether the right hand side ran.
+    let divide_by_zero = |pool: &mut M::Pool| {
+        pool.push_literal(1);
+        pool.push_literal(0);
+        pool.push_binop(BinOp::Div);
+    };
+    let literal = |value: Value| {
+        move |pool: &mut M::Pool| {
+            pool.push_literal(value);
+        }
+    };
+
+    let mut pool = M::Pool::default();
+    pool.push_and(literal(0.into()), divide_by_zero);
+    assert_eq!(M::create_and_run(&pool).unwrap(), vec![Value::Int(0)]);
+
+    let mut pool = M::Pool::default();
+    pool.push_or(literal("a".into()), divide_by_zero);
+    assert_eq!(
+        M::create_and_run(&pool).unwrap(),
+        vec![Value::Str("a".into())]
+    );
+
+    let mut pool = M::Pool::default();
+    pool.push_and(literal(1.into()), literal("b".into()));
+    assert_eq!(
+        M::create_and_run(&pool).unwrap(),
+        vec![Value::Str("b".into())]
+    );
+
+    let mut pool = M::Pool::default();
+    pool.push_or(literal("".into()), literal("b".into()));
+    assert_eq!(
+        M::create_and_run(&pool).unwrap(),
+        vec![Value::Str("b".into())]
+    );
+
+    // `(0 or 2) and 3`
+    let mut pool = M::Pool::default();
+    pool.push_and(
+        |lhs| lhs.push_or(literal(0.into()), literal(2.into())),
+        literal(3.into()),
+    );
+    eprintln!("{pool}");
+    assert_eq!(M::create_and_run(&pool).unwrap(), vec![Value::Int(3)]);
+}
+
+fn check_append<M: Machine>() {
+    let mut whole = M::Pool::default();
+    if_else(&mut whole, 1);
+    count_down(&mut whole);
+
+    let mut fragment = M::Pool::default();
+    count_down(&mut fragment);
+    let mut pool = M::Pool::default();
+    if_else(&mut pool, 1);
+    pool.append(fragment);
+
+    assert_eq!(pool.decode(), whole.decode());
+    assert_eq!(pool.constants(), whole.constants());
+    let stack = M::create_and_run(&pool).unwrap();
+    assert_eq!(
+        stack,
+        vec![
+            Value::Str("if".into()),
+            Value::Int(3),
+            Value::Int(2),
+            Value::Int(1),
+            Value::Int(0)
+        ]
+    );
+}
+
+fn check_format<M: Machine>() {
+    // The fragment's template lands at a different constant index once appended.
+    let mut fragment = M::Pool::default();
+    fragment.push_literal(2);
+    fragment.push_literal(0.5);
+    fragment.push_binop(BinOp::Mul);
+    fragment.push_to_str();
+    fragment.push_literal(" 3 ");
+    fragment.push_to_int();
+    fragment.push_format("{} of {}: {{{}}}");
+
+    let mut pool = M::Pool::default();
+    pool.push_literal("one");
+    pool.push_literal("ignored");
+    pool.push_format("{}");
+    pool.append(fragment);
+
+    eprintln!("{pool}");
+    let stack = M::create_and_run(&pool).unwrap();
+    assert_eq!(
+        stack,
+        vec![
+            Value::Str("one".into()),
+            Value::Str("ignored of 1.0: {3}".into())
+        ]
+    );
+}
+
+fn check_outlives_pool<M: Machine>() {
+    let mut vm = {
+        let mut pool = M::Pool::default();
+        count_down(&mut pool);
+        M::new(&pool)
+    };
+    vm.run().unwrap();
+    assert_eq!(
+        vm.into_stack(),
+        vec![Value::Int(3), Value::Int(2), Value::Int(1), Value::Int(0)]
+    );
+}
+
+fn check_list<M: Machine>() {
+    let mut pool = M::Pool::default();
+    pool.push_literal(1);
+    pool.push_literal(2);
+    pool.push_build_list(2);
+    pool.push_literal("three");
+    pool.push_append();
+    pool.push_dup();
+    pool.push_literal(0);
+    pool.push_literal(5.5);
+    pool.push_store_index();
+    pool.push_dup();
+    pool.push_literal(2);
+    pool.push_index();
+
+    let mut vm = M::new(&pool);
+    vm.run().unwrap();
+    assert_eq!(
+        vm.into_stack(),
+        vec![
+            Value::list(vec![Value::Float(5.5), Value::Int(2), "three".into()]),
+            "three".into()
+        ]
+    );
+}
+
+/// Mutating a loaded list or map constant must leave the pool's copy alone.
+fn check_constants_run_twice<M: Machine>() {
+    let mut pool = M::Pool::default();
+    pool.push_const(Value::list(vec![Value::list(vec![1.into()])]));
+    pool.push_dup();
+    pool.push_literal(0);
+    pool.push_index();
+    pool.push_literal(2);
+    pool.push_append();
+    pool.push_append();
+    pool.push_const(Value::map(Map::default()));
+    pool.push_literal("k");
+    pool.push_literal(3);
+    pool.push_set_item();
+    let constants: Vec<Value> = pool.constants().iter().map(Value::deep_copy).collect();
+
+    for _ in 0..2 {
+        let mut vm = M::new(&pool);
+        vm.run().unwrap();
+        assert_eq!(
+            vm.into_stack(),
+            vec![Value::list(vec![
+                Value::list(vec![1.into(), 2.into()]),
+                Value::list(vec![1.into(), 2.into()]),
+            ])]
+        );
+    }
+    assert_eq!(pool.constants(), constants);
+    assert_eq!(
+        pool.constants()[0],
+        Value::list(vec![Value::list(vec![1.into()])])
+    );
+}
+
+fn check_map<M: Machine>() {
+    let mut pool = M::Pool::default();
+    pool.push_literal("a");
+    pool.push_literal(1);
+    pool.push_literal(2);
+    pool.push_literal("b");
+    pool.push_build_map(2);
+    pool.push_dup();
+    pool.push_literal("a");
+    pool.push_literal(3.5);
+    pool.push_set_item();
+    pool.push_dup();
+    pool.push_literal(2);
+    pool.push_get_item();
+
+    let mut vm = M::new(&pool);
+    vm.run().unwrap();
+    let entries = [
+        (Key::Str("a".into()), Value::Float(3.5)),
+        (Key::Int(2), "b".into()),
+    ];
+    assert_eq!(
+        vm.into_stack(),
+        vec![Value::map(entries.into_iter().collect()), "b".into()]
+    );
+}
+
+/// A list or map can be shared but never stored inside itself.
+fn check_cycles<M: Machine>() {
+    let mut pool = M::Pool::default();
+    pool.push_build_list(0);
+    pool.push_dup();
+    pool.push_dup();
+    pool.push_append();
+    assert_eq!(M::create_and_run(&pool), Err(Error::Cycle("Append")));
+
+    // `a.append([a])`
+    let mut pool = M::Pool::default();
+    pool.push_build_list(0);
+    pool.push_dup();
+    pool.push_build_list(1);
+    pool.push_append();
+    assert_eq!(M::create_and_run(&pool), Err(Error::Cycle("Append")));
+
+    let mut pool = M::Pool::default();
+    pool.push_literal(0);
+    pool.push_build_list(1);
+    pool.push_dup();
+    pool.push_literal(0);
+    pool.push_over();
+    pool.push_store_index();
+    assert_eq!(M::create_and_run(&pool), Err(Error::Cycle("StoreIndex")));
+
+    let mut pool = M::Pool::default();
+    pool.push_build_map(0);
+    pool.push_dup();
+    pool.push_literal("k");
+    pool.push_over();
+    pool.push_set_item();
+    assert_eq!(M::create_and_run(&pool), Err(Error::Cycle("SetItem")));
+
+    // Appending the same empty list twice.
+    let mut pool = M::Pool::default();
+    pool.push_build_list(0);
+    pool.push_build_list(0);
+    pool.push_dup();
+    pool.push_rot3();
+    pool.push_append();
+    pool.push_swap();
+    pool.push_append();
+    pool.push_to_str();
+    assert_eq!(M::create_and_run(&pool), Ok(vec!["[[], []]".into()]));
+}
+
+/// Runs each check on both encodings, with both `Value` and `Packed` slots.
+macro_rules! check_encodings {
+    ($($check:ident),* $(,)?) => {
+        check_encodings!(@encoding two_byte: $($check),*);
+        check_encodings!(@encoding variable_length: $($check),*);
+    };
+    (@encoding $encoding:ident: $($check:ident),*) => {
+        mod $encoding {
+            use crate::{packed::Packed, $encoding::vm::Vm};
+
+            $(
+                #[test]
+                fn $check() {
+                    super::$check::<Vm>();
+                    super::$check::<Vm<Packed>>();
+                }
+            )*
+        }
+    };
+}
+
+check_encodings!(
+    check_count_down,
+    check_if_else,
+    check_nop,
+    check_short_circuit,
+    check_format,
+    check_append,
+    check_outlives_pool,
+    check_list,
+    check_constants_run_twice,
+    check_map,
+    check_cycles,
+);
+
+mod differential {
+    use crate::{differential::assert_agree, program::Stmt, BinOp, Value};
+
+    #[test]
+    fn literals() {
+        assert_agree(&[
+            Stmt::Literal(Value::Int(1)),
+            Stmt::Literal(Value::Float(f64::NAN)),
+            Stmt::Literal("Hello, World!".into()),
+            Stmt::Nop,
+        ]);
+    }
+
+    #[test]
+    fn count_down() {
+        assert_agree(&[
+            Stmt::Literal(Value::Int(4)),
+            Stmt::While(
+                vec![
+                    Stmt::Literal(Value::Int(1)),
+                    Stmt::BinOp(BinOp::Sub),
+                    Stmt::Dup,
+                ],
+                vec![Stmt::Dup],
+            ),
+        ]);
+    }
+
+    #[test]
+    fn branches() {
+        for condition in [0, 1] {
+            assert_agree(&[
+                Stmt::Literal(Value::Int(condition)),
+                Stmt::IfElse(
+                    vec![Stmt::Literal("if".into())],
+                    vec![Stmt::Literal("else".into()), Stmt::Dup],
+                ),
+                Stmt::Literal(Value::Int(condition)),
+                Stmt::If(vec![Stmt::Literal(Value::Float(0.5))]),
+            ]);
+        }
+    }
+
+    #[test]
+    fn dup_empty_stack() {
+        assert_agree(&[Stmt::Dup]);
+    }
+
+    #[test]
+    fn unsupported_operands() {
+        assert_agree(&[
+            Stmt::Literal("a".into()),
+            Stmt::Literal(Value::Float(1.0)),
+            Stmt::BinOp(BinOp::Sub),
+        ]);
+    }
+}
+
+mod generator {
+    use crate::{
+        differential::{fuzz, run_with_fuel},
+        generator::{shrink, Generator},
+        program::{self, Stmt},
+        two_byte::{bytecode::Pool, vm},
+        BinOp, Error, Value,
+    };
+
+    #[test]
+    fn deterministic() {
+        for seed in 0..32 {
+            let lhs = Generator::new(seed).program();
+            let rhs = Generator::new(seed).program();
+            assert_eq!(format!("{lhs:?}"), format!("{rhs:?}"));
+        }
+    }
+
+    #[test]
+    fn well_typed() {
+        for seed in 0..500 {
+            let program = Generator::new(seed).program();
+            let pool: Pool = program::lower(&program);
+            match vm::create_and_run(&pool) {
+                Ok(_) | Err(Error::DivisionByZero | Error::IntegerOverflow) => (),
+                Err(err) => panic!("seed {seed} failed with {err}:\n{pool}"),
+            }
+        }
+    }
+
+    #[test]
+    fn machines_agree() {
+        fuzz(0..1000, Generator::new);
+    }
+
+    #[test]
+    fn machines_agree_ill_typed() {
+        fuzz(0..1000, |seed| Generator {
+            ill_typed: true,
+            ..Generator::new(seed)
+        });
+    }
+
+    #[test]
+    fn shrinks_to_minimal() {
+        let divides_by_zero = |program: &[Stmt]| {
+            let pool: Pool = program::lower(program);
+            run_with_fuel(&pool) == Some(Err(Error::DivisionByZero))
+        };
+        let program = (0..1000)
+            .map(|seed| Generator::new(seed).program())
+            .find(|program| divides_by_zero(program))
+            .unwrap();
+
+        let shrunk = shrink(program, divides_by_zero);
+        assert_eq!(shrunk.len(), 3, "{shrunk:?}");
+        assert_eq!(shrunk[1], Stmt::Literal(Value::Int(0)));
+        assert!(matches!(shrunk[2], Stmt::BinOp(BinOp::Div | BinOp::Mod)));
+    }
+}
+
+mod instr {
+    use crate::{
+        generator::Generator,
+        instr::{Instr, Target},
+        program, threaded, two_byte, variable_length, Assembler, BinOp, Error, UnaryOp,
+    };
+
+    fn round_trip<A: Assembler>(pool: &A) -> Vec<Instr> {
+        let instrs = pool.decode().unwrap();
+        let encoded = A::from_instrs(&instrs, pool.constants().to_vec());
+        assert_eq!(&*encoded.to_string(), &*pool.to_string());
+        assert_eq!(encoded.decode().unwrap(), instrs);
+        instrs
+    }
+
+    #[test]
+    fn lossless() {
+        for seed in 0..200 {
+            let program = Generator::new(seed).program();
+            let mut two_byte: two_byte::bytecode::Pool = program::lower(&program);
+            two_byte.relax();
+            let mut variable_length: variable_length::bytecode::Pool = program::lower(&program);
+            variable_length.relax();
+
+            let two_byte_instrs = round_trip(&two_byte);
+            let encoded = two_byte::bytecode::Pool::from_instrs(
+                &two_byte_instrs,
+                two_byte.constants.to_vec(),
+            );
+            assert_eq!(encoded.bytes, two_byte.bytes);
+
+            let variable_length_instrs = round_trip(&variable_length);
+            let encoded = variable_length::bytecode::Pool::from_instrs(
+                &variable_length_instrs,
+                variable_length.constants.to_vec(),
+            );
+            assert_eq!(encoded.as_bytes(), variable_length.as_bytes());
+
+            assert_eq!(two_byte_instrs, variable_length_instrs);
+        }
+    }
+
+    #[test]
+    fn across_encodings() {
+        let instrs = [
+            Instr::LoadConst(0),
+            Instr::Dup,
+            Instr::PopJumpIfFalse(Target(6)),
+            Instr::LoadConst(1),
+            Instr::BinOp(BinOp::Add),
+            Instr::UnaryOp(UnaryOp::Neg),
+            Instr::Jump(Target(7)),
+            Instr::Nop,
+        ];
+        let constants = vec![1.into(), 2.into()];
+        let two_byte = two_byte::bytecode::Pool::from_instrs(&instrs, constants.clone());
+        let variable_length = variable_length::bytecode::Pool::from_instrs(&instrs, constants);
+
+        eprintln!("{two_byte}\n{variable_length}");
+        assert_eq!(two_byte.decode().unwrap(), instrs);
+        assert_eq!(variable_length.decode().unwrap(), instrs);
+        assert_eq!(
+            two_byte::vm::create_and_run(&two_byte).unwrap(),
+            vec![(-3).into()]
+        );
+        assert_eq!(
+            variable_length::vm::create_and_run(&variable_length).unwrap(),
+            vec![(-3).into()]
+        );
+    }
+
+    #[test]
+    fn conditional_jumps() {
+        let instrs = [
+            Instr::LoadConst(0),
+            Instr::JumpIfTrueOrPop(Target(3)),
+            Instr::LoadConst(1),
+            Instr::Dup,
+            Instr::JumpIfFalseOrPop(Target(6)),
+            Instr::PopJumpIfTrue(Target(7)),
+            Instr::LoadConst(0),
+            Instr::LoadConst(1),
+        ];
+        let constants = vec!["".into(), "x".into()];
+        let two_byte = two_byte::bytecode::Pool::from_instrs(&instrs, constants.clone());
+        let variable_length =
+            variable_length::bytecode::Pool::from_instrs(&instrs, constants.clone());
+        let code = threaded::code::Code::from_instrs(&instrs, &constants).unwrap();
+
+        eprintln!("{two_byte}\n{variable_length}");
+        assert_eq!(two_byte.decode().unwrap(), instrs);
+        assert_eq!(variable_length.decode().unwrap(), instrs);
+        let expected = vec!["x".into()];
+        assert_eq!(two_byte::vm::create_and_run(&two_byte).unwrap(), expected);
+        assert_eq!(
+            variable_length::vm::create_and_run(&variable_length).unwrap(),
+            expected
+        );
+        assert_eq!(threaded::vm::create_and_run(&code).unwrap(), expected);
+    }
+
+    #[test]
+    fn stack_ops() {
+        let instrs = [
+            Instr::LoadConst(0),
+            Instr::LoadConst(1),
+            Instr::Over,
+            Instr::Rot3,
+            Instr::Swap,
+            Instr::Dup,
+            Instr::PopN(2),
+            Instr::Pop,
+        ];
+        let constants = vec![1.into(), 2.into()];
+        let two_byte = two_byte::bytecode::Pool::from_instrs(&instrs, constants.clone());
+        let variable_length =
+            variable_length::bytecode::Pool::from_instrs(&instrs, constants.clone());
+        let code = threaded::code::Code::from_instrs(&instrs, &constants).unwrap();
+
+        eprintln!("{two_byte}\n{variable_length}");
+        assert_eq!(two_byte.decode().unwrap(), instrs);
+        assert_eq!(variable_length.decode().unwrap(), instrs);
+        let expected = vec![1.into()];
+        assert_eq!(two_byte::vm::create_and_run(&two_byte).unwrap(), expected);
+        assert_eq!(
+            variable_length::vm::create_and_run(&variable_length).unwrap(),
+            expected
+        );
+        assert_eq!(threaded::vm::create_and_run(&code).unwrap(), expected);
+
+        // A count past 16 bits needs an `ExtendedArg` in the two byte encoding.
+        let wide = [Instr::PopN(70_000)];
+        let pool = two_byte::bytecode::Pool::from_instrs(&wide, vec![]);
+        assert_eq!(pool.len(), 2 * two_byte::bytecode::OpCode::INSTRUCTION_SIZE);
+        assert_eq!(pool.decode().unwrap(), wide);
+    }
+
+    #[test]
+    fn malformed() {
+        let mut pool = two_byte::bytecode::Pool::default();
+        pool.push_literal(1);
+        pool.bytes.pop();
+        assert_eq!(pool.decode(), Err(Error::UnexpectedEnd));
+
+        let mut pool = variable_length::bytecode::Pool::default();
+        pool.push_literal(1);
+        pool.push_jump(2);
+        assert_eq!(pool.decode(), Err(Error::InvalidJump(2)));
+    }
+}
+
+mod constants {
+    use crate::{
+        assembler, constants::Constants, two_byte::bytecode::Pool as TwoByte,
+        variable_length::bytecode::Pool as VariableLength, Assembler, BinOp, Value,
+    };
+    use std::rc::Rc;
+
+    fn check_float_identity<A: Assembler>(pool: &mut A) {
+        let nan = pool.push_literal(f64::NAN);
+        assert_eq!(pool.push_literal(f64::NAN), nan);
+        let zero = pool.push_literal(0.0);
+        let negative_zero = pool.push_literal(-0.0);
+        assert_ne!(zero, negative_zero);
+        assert_eq!(pool.push_literal(-0.0), negative_zero);
+        assert_eq!(pool.constants().len(), 3);
+    }
+
+    #[test]
+    fn float_identity() {
+        check_float_identity(&mut TwoByte::default());
+        check_float_identity(&mut VariableLength::default());
+    }
+
+    fn check_interned<A: Assembler>(pool: &mut A) {
+        pool.push_literal("interned");
+        pool.push_literal(String::from("interned"));
+        pool.push_dup();
+        pool.push_binop(BinOp::Add);
+        assert_eq!(pool.constants().len(), 1);
+
+        let stack = assembler::run(pool).unwrap();
+        let [Value::Str(first), Value::Str(concat)] = &stack[..] else {
+            panic!("unexpected stack {stack:?}");
+        };
+        let [Value::Str(constant)] = pool.constants() else {
+            panic!("unexpected constants {:?}", pool.constants());
+        };
+        assert!(Rc::ptr_eq(first, constant));
+        assert_eq!(&**concat, "internedinterned");
+    }
+
+    #[test]
+    fn strings_are_interned() {
+        check_interned(&mut TwoByte::default());
+        check_interned(&mut VariableLength::default());
+    }
+
+    #[test]
+    fn kinds_stay_apart() {
+        let mut constants = Constants::default();
+        let int = constants.insert(Value::Int(1));
+        let float = constants.insert(Value::Float(1.0));
+        let str = constants.insert("1".into());
+        assert_eq!([int, float, str], [0, 1, 2]);
+        assert_eq!(constants.find(&Value::Int(1)), Some(0));
+        assert_eq!(constants.find(&Value::Int(2)), None);
+    }
+
+    #[test]
+    fn many_constants() {
+        let mut constants = Constants::default();
+        for (index, int) in (0..200_000).enumerate() {
+            assert_eq!(constants.insert(Value::Int(int)), index);
+        }
+        assert_eq!(constants.insert(Value::Int(1234)), 1234);
+    }
+
+    #[test]
+    fn from_vec_keeps_duplicates() {
+        let mut constants = Constants::from(vec![1.into(), 2.into(), 1.into()]);
+        assert_eq!(constants.len(), 3);
+        assert_eq!(constants.insert(1.into()), 0);
+        assert_eq!(constants.insert(3.into()), 3);
+    }
+}
+
+mod packed {
+    use crate::{packed::Packed, two_byte, BinOp, Machine, Slot, Value};
+    use std::rc::Rc;
+
+    fn round_trip(value: &Value) -> Value {
+        let packed = Packed::from_value(value.clone());
+        let cloned = packed.clone();
+        drop(packed);
+        cloned.into_value()
+    }
+
+    #[test]
+    fn fits_in_a_word() {
+        assert_eq!(size_of::<Packed>(), 8);
+    }
+
+    #[test]
+    fn ints() {
+        let bound = 1 << 49;
+        for int in [
+            0,
+            1,
+            -1,
+            bound - 1,
+            -bound,
+            bound,
+            -bound - 1,
+            i64::MAX,
+            i64::MIN,
+        ] {
+            assert_eq!(round_trip(&Value::Int(int)), Value::Int(int));
+        }
+    }
+
+    #[test]
+    fn floats_keep_their_bits() {
+        let floats = [
+            0.0,
+            -0.0,
+            f64::INFINITY,
+            f64::NAN,
+            -f64::NAN,
+            f64::from_bits(0x7FFC_0000_0000_0001),
+            f64::from_bits(0xFFFF_FFFF_FFFF_FFFF),
+        ];
+        for float in floats {
+            let Value::Float(packed) = round_trip(&Value::Float(float)) else {
+                panic!("{float} changed kind");
+            };
+            assert_eq!(packed.to_bits(), float.to_bits());
+        }
+    }
+
+    #[test]
+    fn strings_are_shared() {
+        let str: Rc<str> = "shared".into();
+        let packed = Packed::from_value(Value::Str(str.clone()));
+        let clones = vec![packed.clone(); 3];
+        assert_eq!(Rc::strong_count(&str), 2);
+        drop(clones);
+        assert_eq!(packed.is_truthy(), Value::Str(str.clone()).is_truthy());
+        let Value::Str(back) = packed.into_value() else {
+            panic!("expected a str");
+        };
+        assert!(Rc::ptr_eq(&back, &str));
+    }
+
+    #[test]
+    fn machine() {
+        let mut pool = two_byte::bytecode::Pool::default();
+        pool.push_literal("ab");
+        pool.push_literal(1 << 60);
+        pool.push_literal(2);
+        pool.push_binop(BinOp::Mul);
+        pool.push_literal(0.5);
+        pool.push_binop(BinOp::Add);
+
+        let mut vm: two_byte::vm::Vm<Packed> = Machine::new(&pool);
+        vm.run().unwrap();
+        assert_eq!(
+            vm.into_stack(),
+            vec![Value::Str("ab".into()), Value::Float(2.0f64.powi(61) + 0.5)]
+        );
+    }
+}
+
+mod map {
+    use crate::{
+        map::{Key, Map},
+        Error, Value,
+    };
+
+    #[test]
+    fn insertion_order() {
+        let mut map = Map::default();
+        for key in [3, 1, 2] {
+            assert_eq!(map.insert(Key::Int(key), Value::Int(key * 10)), None);
+        }
+        assert_eq!(map.insert(Key::Int(1), Value::Int(0)), Some(Value::Int(10)));
+        let keys: Vec<&Key> = map.keys().collect();
+        assert_eq!(keys, [&Key::Int(3), &Key::Int(1), &Key::Int(2)]);
+        assert_eq!(map.get(&Key::Int(1)), Some(&Value::Int(0)));
+    }
+
+    #[test]
+    fn unordered_equality() {
+        let forward: Map = [
+            (Key::Int(1), Value::Int(1)),
+            (Key::Str("a".into()), "b".into()),
+        ]
+        .into_iter()
+        .collect();
+        let backward: Map = [
+            (Key::Str("a".into()), "b".into()),
+            (Key::Int(1), Value::Int(1)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(forward, backward);
+        assert_eq!(Value::map(forward), Value::map(backward));
+    }
+
+    #[test]
+    fn float_keys() {
+        assert_eq!(
+            Key::try_from(&Value::Float(1.0)),
+            Err(Error::UnhashableKey("float"))
+        );
+        assert_eq!(
+            Key::try_from(&Value::list(vec![])),
+            Err(Error::UnhashableKey("list"))
+        );
+    }
+}
+
+mod display {
+    use crate::{bigint::BigInt, map::Map, Value};
+
+    #[test]
+    fn scalars() {
+        assert_eq!(Value::Int(-3).to_string(), "-3");
+        assert_eq!(
+            Value::big(&BigInt::from(i64::MAX) + &1.into()).to_string(),
+            "9223372036854775808"
+        );
+        assert_eq!(Value::Str("a \"b\"".into()).to_string(), "a \"b\"");
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(Value::Float(1.0).to_string(), "1.0");
+        assert_eq!(Value::Float(-0.25).to_string(), "-0.25");
+        assert_eq!(Value::Float(1e300).to_string(), "1e300");
+        assert_eq!(Value::Float(f64::NAN).to_string(), "nan");
+        assert_eq!(Value::Float(f64::NEG_INFINITY).to_string(), "-inf");
+    }
+
+    #[test]
+    fn collections() {
+        let list = Value::list(vec![1.into(), "a".into(), Value::list(vec![])]);
+        assert_eq!(list.to_string(), "[1, \"a\", []]");
+
+        let map: Map = [("k".into(), list), (2.into(), 0.5.into())]
+            .into_iter()
+            .map(|(key, value): (Value, Value)| ((&key).try_into().unwrap(), value))
+            .collect();
+        assert_eq!(
+            Value::map(map).to_string(),
+            "{\"k\": [1, \"a\", []], 2: 0.5}"
+        );
+        assert_eq!(Value::map(Map::default()).to_string(), "{}");
+    }
+}
+
+mod format {
+    use crate::{format::Template, Error, Value};
+
+    fn render(template: &str, args: &[i64]) -> Result<Value, Error> {
+        let template = Template::try_from(template)?;
+        assert_eq!(template.holes(), args.len());
+        Ok(template.render(args.iter().copied().map(Value::Int)))
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(render("{} + {} = {}", &[1, 2, 3]), Ok("1 + 2 = 3".into()));
+        assert_eq!(render("", &[]), Ok("".into()));
+        assert_eq!(render("{}{}", &[4, 5]), Ok("45".into()));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(render("{{}} {{{}}}", &[1]), Ok("{} {1}".into()));
+        assert_eq!(render("}}{{", &[]), Ok("}{".into()));
+    }
+
+    #[test]
+    fn invalid() {
+        for template in ["{", "}", "{0}", "a {x} b", "{}}"] {
+            assert_eq!(
+                Template::try_from(template),
+                Err(Error::InvalidFormat(template.into())),
+                "{template}"
+            );
+        }
+        assert_eq!(
+            Template::try_from(&Value::Int(1)),
+            Err(Error::UnsupportedOperand {
+                op: "Format",
+                operand: "int"
+            })
+        );
+    }
+}
+
+mod bigint {
+    use crate::{bigint::BigInt, generator::Rng};
+
+    fn big(int: i128) -> BigInt {
+        int.to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn matches_i128() {
+        let mut rng = Rng::new(45);
+        for _ in 0..2000 {
+            let lhs = i128::from(rng.next_u64().cast_signed()) * i128::from(rng.range(-3, 4));
+            let rhs = i128::from(rng.next_u64().cast_signed() >> rng.below(64));
+            let (l, r) = (big(lhs), big(rhs));
+            assert_eq!(&l + &r, big(lhs + rhs));
+            assert_eq!(&l - &r, big(lhs - rhs));
+            assert_eq!(&l & &r, big(lhs & rhs));
+            assert_eq!(&l | &r, big(lhs | rhs));
+            assert_eq!(&l ^ &r, big(lhs ^ rhs));
+            assert_eq!(!&l, big(!lhs));
+            assert_eq!(l.cmp(&r), lhs.cmp(&rhs));
+            let shift = u32::try_from(rng.below(40)).unwrap();
+            assert_eq!(&l >> shift, big(lhs >> shift));
+            assert_eq!(&r << shift, big(rhs << shift));
+            if rhs != 0 {
+                let (quotient, remainder) = l.div_rem(&r).unwrap();
+                assert_eq!((quotient, remainder), (big(lhs / rhs), big(lhs % rhs)));
+            }
+            let narrow = rhs >> 64;
+            assert_eq!(&big(narrow) * &r, big(narrow * rhs));
+            assert_eq!(l.to_i64(), i64::try_from(lhs).ok());
+        }
+    }
+
+    #[test]
+    fn from_f64() {
+        assert_eq!(BigInt::from_f64(-2.75), Some(BigInt::from(-2)));
+        assert_eq!(BigInt::from_f64(0.5e-310), Some(BigInt::default()));
+        assert_eq!(
+            BigInt::from_f64(1e20),
+            Some(big(100_000_000_000_000_000_000))
+        );
+        assert_eq!(
+            BigInt::from_f64(-(2f64.powi(80))),
+            Some(-&BigInt::from(2).pow(80))
+        );
+        assert_eq!(BigInt::from_f64(f64::INFINITY), None);
+        assert_eq!(BigInt::from_f64(f64::NAN), None);
+    }
+
+    #[test]
+    fn long_division() {
+        let dividend = BigInt::from(3).pow(200);
+        let divisor = BigInt::from(7).pow(40);
+        let (quotient, remainder) = dividend.div_rem(&divisor).unwrap();
+        assert!(remainder < divisor);
+        assert_eq!(&(&quotient * &divisor) + &remainder, dividend);
+    }
+
+    /// Builds a magnitude from little-endian limbs.
+    fn from_limbs(limbs: &[u32]) -> BigInt {
+        limbs.iter().rev().fold(BigInt::default(), |big, &limb| {
+            &(&big << 32) | &BigInt::from(i64::from(limb))
+        })
+    }
+
+    fn check_div_rem(dividend: &BigInt, divisor: &BigInt) {
+        let (quotient, remainder) = dividend.div_rem(divisor).unwrap();
+        assert_eq!(&(&quotient * divisor) + &remainder, *dividend);
+        let zero = BigInt::default();
+        assert!(remainder == zero || (remainder < zero) == (*dividend < zero));
+        let magnitude = |big: &BigInt| if *big < zero { -big } else { big.clone() };
+        assert!(magnitude(&remainder) < magnitude(divisor));
+    }
+
+    #[test]
+    fn multi_limb_division() {
+        // Estimates a quotient limb one too large and has to add the divisor back.
+        check_div_rem(
+            &from_limbs(&[0, 0, 0x8000_0000, 0x7FFF_FFFF]),
+            &from_limbs(&[1, 0, 0x8000_0000]),
+        );
+        check_div_rem(
+            &from_limbs(&[0, 0xFFFF_FFFE, 0, 0x8000_0000]),
+            &from_limbs(&[0xFFFF_FFFF, 0x8000_0000]),
+        );
+        let mut rng = Rng::new(45);
+        for _ in 0..500 {
+            let dividend = random_limbs(&mut rng, 12);
+            let divisor = random_limbs(&mut rng, 6);
+            if divisor != BigInt::default() {
+                check_div_rem(&dividend, &divisor);
+                check_div_rem(&-&dividend, &divisor);
+            }
+        }
+    }
+
+    /// Up to `max_len` limbs, biased towards all zero and all one limbs.
+    fn random_limbs(rng: &mut Rng, max_len: usize) -> BigInt {
+        let limbs: Vec<u32> = (0..=rng.below(max_len))
+            .map(|_| match rng.below(4) {
+                0 => 0,
+                1 => u32::MAX,
+                _ => u32::try_from(rng.next_u64() >> 32).unwrap(),
+            })
+            .collect();
+        from_limbs(&limbs)
+    }
+
+    #[test]
+    fn text() {
+        for str in [
+            "0",
+            "-1",
+            "4294967296",
+            "-18446744073709551616",
+            "1000000000000000000000000000",
+        ] {
+            assert_eq!(str.parse::<BigInt>().unwrap().to_string(), str);
+        }
+        assert_eq!("+12".parse::<BigInt>(), Ok(BigInt::from(12)));
+        assert!("".parse::<BigInt>().is_err());
+        assert!("-".parse::<BigInt>().is_err());
+        assert!("1e5".parse::<BigInt>().is_err());
+        assert_eq!(BigInt::from(2).pow(64).wrapping_to_i64(), 0);
+        assert_eq!((-&BigInt::from(2).pow(63)).to_i64(), Some(i64::MIN));
+    }
+}
@@ -0,0 +1,207 @@
+use super::vm::State;
+use crate::{format::Template, instr::Instr, Assembler, Error, Value};
+use std::rc::Rc;
+
+pub type Handler = Box<dyn Fn(&mut State) -> Result<(), Error>>;
+
+/// Pre-decoded instructions, jump targets are indices into `handlers`.
+///
+/// Handlers are shared, so cloning `Code` is cheap.
+#[derive(Clone, Default)]
+pub struct Code {
+    pub handlers: Rc<[Handler]>,
+}
+
+impl Code {
+    pub fn from_pool<A: Assembler>(pool: &A) -> Result<Self, Error> {
+        Self::from_instrs(&pool.decode()?, pool.constants())
+    }
+    #[allow(clippy::too_many_lines)]
+    pub fn from_instrs(instrs: &[Instr], constants: &[Value]) -> Result<Self, Error> {
+        let mut handlers: Vec<Handler> = Vec::with_capacity(instrs.len());
+        for instr in instrs {
+            handlers.push(match *instr {
+                Instr::Nop => Box::new(|_| Ok(())),
+                Instr::Dup => Box::new(|state| {
+                    let top = state.stack.last().ok_or(Error::StackUnderflow)?;
+                    state.stack.push(top.clone());
+                    Ok(())
+                }),
+                Instr::Pop => Box::new(|state| state.pop().map(drop)),
+                Instr::Swap => Box::new(|state| {
+                    state.top_mut(2)?.swap(0, 1);
+                    Ok(())
+                }),
+                Instr::Rot3 => Box::new(|state| {
+                    state.top_mut(3)?.rotate_right(1);
+                    Ok(())
+                }),
+                Instr::Over => Box::new(|state| {
+                    let second = state.top_mut(2)?[0].clone();
+                    state.stack.push(second);
+                    Ok(())
+                }),
+                Instr::PopN(len) => Box::new(move |state| state.split_top(len as usize).map(drop)),
+                Instr::Print => Box::new(|state| {
+                    let value = state.pop()?;
+                    state.output.print(&value)
+                }),
+                Instr::ToStr => Box::new(|state| {
+                    let value = state.pop()?;
+                    state.stack.push(value.to_str());
+                    Ok(())
+                }),
+                Instr::ToInt => Box::new(|state| {
+                    let value = state.pop()?;
+                    state.stack.push(value.to_int()?);
+                    Ok(())
+                }),
+                Instr::ToFloat => Box::new(|state| {
+                    let value = state.pop()?;
+                    state.stack.push(value.to_float()?);
+                    Ok(())
+                }),
+                Instr::Format(index) => {
+                    let index = index as usize;
+                    // A missing or malformed template only fails if the instruction
+                    // is reached, the same as on the stack machines.
+                    let template = constants
+                        .get(index)
+                        .ok_or(Error::InvalidConstant(index))
+                        .and_then(Template::try_from);
+                    Box::new(move |state| {
+                        let template = template.as_ref().map_err(Clone::clone)?;
+                        let args = state.split_top(template.holes())?;
+                        state.stack.push(template.render(args));
+                        Ok(())
+                    })
+                }
+                Instr::BinOp(binop) => Box::new(move |state| {
+                    let rhs = state.pop()?;
+                    let lhs = state.pop()?;
+                    state
+                        .stack
+                        .push(Value::run_binop(lhs, rhs, binop, state.mode)?);
+                    Ok(())
+                }),
+                Instr::UnaryOp(op) => Box::new(move |state| {
+                    let operand = state.pop()?;
+                    state
+                        .stack
+                        .push(Value::run_unaryop(&operand, op, state.mode)?);
+                    Ok(())
+                }),
+                Instr::LoadConst(index) => {
+                    let index = index as usize;
+                    let value = constants
+                        .get(index)
+                        .cloned()
+                        .ok_or(Error::InvalidConstant(index));
+                    Box::new(move |state| {
+                        let value = value.as_ref().map_err(Clone::clone)?;
+                        state.stack.push(value.deep_copy());
+                        Ok(())
+                    })
+                }
+                Instr::BuildList(len) => Box::new(move |state| {
+                    let items = state.split_top(len as usize)?;
+                    state.stack.push(Value::list(items));
+                    Ok(())
+                }),
+                Instr::Index => Box::new(|state| {
+                    let index = state.pop()?;
+                    let list = state.pop()?;
+                    state.stack.push(Value::index(&list, &index)?);
+                    Ok(())
+                }),
+                Instr::StoreIndex => Box::new(|state| {
+                    let value = state.pop()?;
+                    let index = state.pop()?;
+                    let list = state.pop()?;
+                    Value::store_index(&list, &index, value)
+                }),
+                Instr::Len => Box::new(|state| {
+                    let list = state.pop()?;
+                    state.stack.push(Value::length(&list)?);
+                    Ok(())
+                }),
+                Instr::Append => Box::new(|state| {
+                    let value = state.pop()?;
+                    Value::append(state.stack.last().ok_or(Error::StackUnderflow)?, value)
+                }),
+                Instr::BuildMap(len) => Box::new(move |state| {
+                    let len = (len as usize).checked_mul(2).ok_or(Error::StackUnderflow)?;
+                    let items = state.split_top(len)?;
+                    state.stack.push(Value::build_map(items)?);
+                    Ok(())
+                }),
+                Instr::GetItem => Box::new(|state| {
+                    let key = state.pop()?;
+                    let map = state.pop()?;
+                    state.stack.push(Value::get_item(&map, &key)?);
+                    Ok(())
+                }),
+                Instr::SetItem => Box::new(|state| {
+                    let value = state.pop()?;
+                    let key = state.pop()?;
+                    let map = state.pop()?;
+                    Value::set_item(&map, &key, value)
+                }),
+                Instr::HasKey => Box::new(|state| {
+                    let key = state.pop()?;
+                    let map = state.pop()?;
+                    state.stack.push(Value::has_key(&map, &key)?);
+                    Ok(())
+                }),
+                Instr::Keys => Box::new(|state| {
+                    let map = state.pop()?;
+                    state.stack.push(Value::keys(&map)?);
+                    Ok(())
+                }),
+                Instr::Jump(target) => Box::new(move |state| {
+                    state.pc = target.0;
+                    Ok(())
+                }),
+                Instr::PopJumpIfFalse(target) => Box::new(move |state| {
+                    if !state.pop()?.is_truthy() {
+                        state.pc = target.0;
+                    }
+                    Ok(())
+                }),
+                Instr::PopJumpIfTrue(target) => Box::new(move |state| {
+                    if state.pop()?.is_truthy() {
+                        state.pc = target.0;
+                    }
+                    Ok(())
+                }),
+                Instr::JumpIfFalseOrPop(target) => Box::new(move |state| {
+                    if state.stack.last().ok_or(Error::StackUnderflow)?.is_truthy() {
+                        state.stack.pop();
+                    } else {
+                        state.pc = target.0;
+                    }
+                    Ok(())
+                }),
+                Instr::JumpIfTrueOrPop(target) => Box::new(move |state| {
+                    if state.stack.last().ok_or(Error::StackUnderflow)?.is_truthy() {
+                        state.pc = target.0;
+                    } else {
+                        state.stack.pop();
+                    }
+                    Ok(())
+                }),
+            });
+        }
+        Ok(Self {
+            handlers: handlers.into(),
+        })
+    }
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
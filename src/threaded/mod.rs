@@ -0,0 +1,7 @@
+//! An execution engine that decodes bytecode ahead of time, so running
+//! an instruction is a single indirect call instead of decoding bytes and matching on them.
+pub mod code;
+pub mod vm;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,151 @@
+use super::{code::Code, vm};
+use crate::{
+    instr::{Instr, Target},
+    output::{Buffer, Output},
+    two_byte, variable_length, Assembler, BinOp, Error, Value,
+};
+
+fn count_down<A: Assembler>(pool: &mut A) {
+    pool.push_literal(4);
+    pool.push_while_loop(
+        |condition| {
+            condition.push_literal(1);
+            condition.push_binop(BinOp::Sub);
+            condition.push_dup();
+        },
+        |body| {
+            body.push_dup();
+        },
+    );
+}
+
+#[test]
+fn from_two_byte() {
+    let mut pool = two_byte::bytecode::Pool::default();
+    count_down(&mut pool);
+
+    let code = Code::from_pool(&pool).unwrap();
+    let stack = vm::create_and_run(&code).unwrap();
+    assert_eq!(
+        stack,
+        vec![Value::Int(3), Value::Int(2), Value::Int(1), Value::Int(0)]
+    );
+}
+
+#[test]
+fn from_variable_length() {
+    let mut pool = variable_length::bytecode::Pool::default();
+    count_down(&mut pool);
+
+    let code = Code::from_pool(&pool).unwrap();
+    let stack = vm::create_and_run(&code).unwrap();
+    assert_eq!(
+        stack,
+        vec![Value::Int(3), Value::Int(2), Value::Int(1), Value::Int(0)]
+    );
+}
+
+#[test]
+fn constants_are_prefetched() {
+    let mut pool = variable_length::bytecode::Pool::default();
+    pool.push_literal("Hello, ");
+    pool.push_literal("World!");
+    pool.push_binop(BinOp::Add);
+
+    let code = Code::from_pool(&pool).unwrap();
+    drop(pool);
+
+    let stack = vm::create_and_run(&code).unwrap();
+    assert_eq!(stack, vec![Value::Str("Hello, World!".into())]);
+}
+
+#[test]
+fn invalid_jump() {
+    let mut pool = variable_length::bytecode::Pool::default();
+    pool.push_literal(1);
+    pool.push_jump(1);
+
+    assert!(matches!(Code::from_pool(&pool), Err(Error::InvalidJump(1))));
+}
+
+#[test]
+fn runtime_errors() {
+    let mut pool = two_byte::bytecode::Pool::default();
+    pool.push_literal(1);
+    pool.push_literal(0);
+    pool.push_binop(BinOp::Mod);
+
+    let code = Code::from_pool(&pool).unwrap();
+    assert_eq!(vm::create_and_run(&code), Err(Error::DivisionByZero));
+}
+
+#[test]
+fn print() {
+    let mut pool = variable_length::bytecode::Pool::default();
+    pool.push_literal("count");
+    pool.push_print();
+    count_down(&mut pool);
+    pool.push_print();
+
+    let buffer = Buffer::default();
+    let mut vm = vm::Vm::new(&Code::from_pool(&pool).unwrap());
+    vm.state.output = Output::new(buffer.clone());
+    vm.run().unwrap();
+    assert_eq!(buffer.contents(), "count\n0\n");
+    assert_eq!(vm.state.stack, vec![3.into(), 2.into(), 1.into()]);
+}
+
+#[test]
+fn format() {
+    let mut pool = two_byte::bytecode::Pool::default();
+    pool.push_literal("7");
+    pool.push_to_int();
+    pool.push_literal(2);
+    pool.push_binop(BinOp::Mul);
+    pool.push_format("total: {}");
+
+    let code = Code::from_pool(&pool).unwrap();
+    let stack = vm::create_and_run(&code).unwrap();
+    assert_eq!(stack, vec![Value::Str("total: 14".into())]);
+
+    let mut pool = two_byte::bytecode::Pool::default();
+    let skip = pool.push_jump(0);
+    pool.push_format("}");
+    pool.patch_jump(skip);
+    pool.push_literal(1);
+    let code = Code::from_pool(&pool).unwrap();
+    assert_eq!(vm::create_and_run(&code).unwrap(), vec![Value::Int(1)]);
+
+    let mut pool = two_byte::bytecode::Pool::default();
+    pool.push_format("}");
+    let code = Code::from_pool(&pool).unwrap();
+    assert_eq!(
+        vm::create_and_run(&code),
+        Err(Error::InvalidFormat("}".into()))
+    );
+}
+
+#[test]
+fn missing_constant_fails_when_reached() {
+    for missing in [Instr::LoadConst(7), Instr::Format(7)] {
+        let code = Code::from_instrs(&[Instr::Jump(Target(2)), missing, Instr::Nop], &[]).unwrap();
+        assert_eq!(vm::create_and_run(&code), Ok(vec![]));
+
+        let code = Code::from_instrs(&[missing], &[]).unwrap();
+        assert_eq!(vm::create_and_run(&code), Err(Error::InvalidConstant(7)));
+    }
+}
+
+#[test]
+fn constants_run_twice() {
+    let mut pool = variable_length::bytecode::Pool::default();
+    pool.push_const(Value::list(vec![1.into()]));
+    pool.push_literal(1);
+    pool.push_append();
+    let code = Code::from_pool(&pool).unwrap();
+    for _ in 0..2 {
+        let stack = vm::create_and_run(&code).unwrap();
+        assert_eq!(stack, vec![Value::list(vec![1.into(), 1.into()])]);
+    }
+    assert_eq!(pool.constants()[0], Value::list(vec![1.into()]));
+}
@@ -0,0 +1,73 @@
+use super::code::Code;
+use crate::{output::Output, ArithmeticMode, Error, Value};
+
+pub fn create_and_run(code: &Code) -> Result<Vec<Value>, Error> {
+    let mut vm = Vm::new(code);
+    vm.run()?;
+    Ok(vm.state.stack)
+}
+
+/// The part of the machine that handlers can modify.
+#[derive(Debug, Default)]
+pub struct State {
+    pub pc: usize,
+    pub stack: Vec<Value>,
+    pub mode: ArithmeticMode,
+    /// Where `Print` writes.
+    pub output: Output,
+}
+
+impl State {
+    pub fn pop(&mut self) -> Result<Value, Error> {
+        self.stack.pop().ok_or(Error::StackUnderflow)
+    }
+    /// Pops the top `len` values, keeping them in push order.
+    pub fn split_top(&mut self, len: usize) -> Result<Vec<Value>, Error> {
+        let at = self
+            .stack
+            .len()
+            .checked_sub(len)
+            .ok_or(Error::StackUnderflow)?;
+        Ok(self.stack.split_off(at))
+    }
+    /// The top `len` values, in push order.
+    pub fn top_mut(&mut self, len: usize) -> Result<&mut [Value], Error> {
+        let at = self
+            .stack
+            .len()
+            .checked_sub(len)
+            .ok_or(Error::StackUnderflow)?;
+        Ok(&mut self.stack[at..])
+    }
+}
+
+pub struct Vm {
+    pub code: Code,
+    pub state: State,
+}
+
+impl Vm {
+    #[must_use]
+    pub fn new(code: &Code) -> Self {
+        Self {
+            code: code.clone(),
+            state: State::default(),
+        }
+    }
+    pub fn run(&mut self) -> Result<(), Error> {
+        while !self.is_finished() {
+            self.run_next()?;
+        }
+        Ok(())
+    }
+    #[inline]
+    pub fn run_next(&mut self) -> Result<(), Error> {
+        let handler = &self.code.handlers[self.state.pc];
+        self.state.pc += 1;
+        handler(&mut self.state)
+    }
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.state.pc >= self.code.len()
+    }
+}
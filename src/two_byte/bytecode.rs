@@ -1,4 +1,9 @@
-use crate::{BinOp, Value};
+use super::vm::Vm;
+use crate::{
+    constants::Constants,
+    instr::{self, Instr, Target},
+    Assembler, BinOp, Error, UnaryOp, Value,
+};
 use std::{fmt, ops::Deref};
 
 #[derive(Debug, Clone, Copy)]
@@ -15,16 +20,65 @@ pub enum OpCode {
     Jump,
     PopJumpIfFalse,
 
+    /// Supplies the high bits of the following instruction's operand.
+    ExtendedArg,
+
+    BuildList,
+    Index,
+    StoreIndex,
+    Len,
+    Append,
+    BuildMap,
+    GetItem,
+    SetItem,
+    HasKey,
+    Keys,
+    UnaryOp,
+    PopJumpIfTrue,
+    JumpIfFalseOrPop,
+    JumpIfTrueOrPop,
+    Pop,
+    Swap,
+    Rot3,
+    Over,
+    PopN,
+    Print,
+    ToStr,
+    ToInt,
+    ToFloat,
+    Format,
+
     LEN,
 }
 
+impl OpCode {
+    pub const INSTRUCTION_SIZE: usize = 3;
+
+    /// Appends `operand` to the bits collected from preceding `ExtendedArg`s.
+    #[must_use]
+    pub fn widen(prefix: Option<u32>, operand: u16) -> u32 {
+        prefix.map_or(0, |high| high << 16) | u32::from(operand)
+    }
+    /// The signed jump offset encoded by `operand` and its `ExtendedArg` prefix, if any.
+    #[must_use]
+    pub fn offset(prefix: Option<u32>, operand: u16) -> isize {
+        match prefix {
+            Some(_) => {
+                let wide = Self::widen(prefix, operand).to_le_bytes();
+                isize::try_from(i32::from_le_bytes(wide)).unwrap()
+            }
+            None => i16::from_le_bytes(operand.to_le_bytes()).into(),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
-pub struct Pool<'a> {
+pub struct Pool {
     pub bytes: Vec<u8>,
-    pub constants: Vec<Value<'a>>,
+    pub constants: Constants,
 }
 
-impl<'a> Pool<'a> {
+impl Pool {
     pub fn push(&mut self, opcode: OpCode, bytes: [u8; 2]) {
         self.bytes.push(opcode as u8);
         self.bytes.push(bytes[0]);
@@ -33,109 +87,434 @@ impl<'a> Pool<'a> {
     pub fn push_u16(&mut self, opcode: OpCode, value: u16) {
         self.push(opcode, value.to_le_bytes());
     }
-    pub fn push_const(&mut self, val: Value<'a>) {
+    pub fn push_i16(&mut self, opcode: OpCode, value: i16) {
+        self.push(opcode, value.to_le_bytes());
+    }
+    /// Pushes `opcode` behind an `ExtendedArg` carrying the high half of `operand`.
+    pub fn push_wide(&mut self, opcode: OpCode, operand: u32) {
+        let [low, high, extended_low, extended_high] = operand.to_le_bytes();
+        self.push(OpCode::ExtendedArg, [extended_low, extended_high]);
+        self.push(opcode, [low, high]);
+    }
+    pub fn push_const(&mut self, val: Value) {
         let index = self.insert_const(val);
-        self.push_u16(OpCode::LoadConst, index);
+        self.push_operand(OpCode::LoadConst, index);
+    }
+    /// Pushes `opcode`, prefixed with an `ExtendedArg` if `operand` doesn't fit 16 bits.
+    pub fn push_operand(&mut self, opcode: OpCode, operand: u32) {
+        match u16::try_from(operand) {
+            Ok(operand) => self.push_u16(opcode, operand),
+            Err(_) => self.push_wide(opcode, operand),
+        }
+    }
+    pub fn push_format(&mut self, template: &str) {
+        let index = self.insert_const(Value::Str(template.into()));
+        self.push_operand(OpCode::Format, index);
+    }
+    pub fn push_pop_n(&mut self, len: u32) {
+        self.push_operand(OpCode::PopN, len);
+    }
+    pub fn push_build_list(&mut self, len: u32) {
+        self.push_operand(OpCode::BuildList, len);
+    }
+    pub fn push_build_map(&mut self, len: u32) {
+        self.push_operand(OpCode::BuildMap, len);
     }
     #[inline]
-    pub fn push_literal<V: Into<Value<'a>>>(&mut self, val: V) {
+    pub fn push_literal<V: Into<Value>>(&mut self, val: V) {
         self.push_const(val.into());
     }
     pub fn push_binop(&mut self, binop: BinOp) {
         self.push(OpCode::BinOp, [binop as u8, 0]);
     }
+    #[inline]
+    pub fn push_unaryop(&mut self, op: UnaryOp) {
+        self.push(OpCode::UnaryOp, [op as u8, 0]);
+    }
     pub fn push_zeroed(&mut self, op_code: OpCode) {
         self.push(op_code, [0, 0]);
     }
-    pub fn insert_const(&mut self, val: Value<'a>) -> u16 {
-        if let Some(index) = self.find_const(&val) {
-            return index;
-        }
-        self.constants.push(val);
-        self.constants.len() as u16 - 1
+    pub fn insert_const(&mut self, val: Value) -> u32 {
+        u32::try_from(self.constants.insert(val)).unwrap()
     }
     #[must_use]
-    pub fn find_const(&self, target: &Value) -> Option<u16> {
-        for (index, value) in self.constants.iter().enumerate() {
-            if value == target {
-                return Some(index as u16);
-            }
-        }
-        None
+    pub fn find_const(&self, target: &Value) -> Option<u32> {
+        self.constants
+            .find(target)
+            .map(|index| u32::try_from(index).unwrap())
     }
     #[must_use]
-    pub fn get_const(&self, index: u16) -> Option<&Value<'a>> {
+    pub fn get_const(&self, index: u32) -> Option<&Value> {
         self.constants.get(index as usize)
     }
-    pub fn push_jump(&mut self, pos: u16) -> usize {
-        self.push_u16(OpCode::Jump, pos);
-        self.len() - 3
+    /// Jumps are always pushed with an `ExtendedArg` prefix so that `patch_jump`
+    /// can reach any target; `relax` drops the ones that turn out unnecessary.
+    pub fn push_jump(&mut self, pos: usize) -> usize {
+        self.push_wide_jump(OpCode::Jump, pos)
     }
-    pub fn push_pop_jump_if_false(&mut self, pos: u16) -> usize {
-        self.push_u16(OpCode::PopJumpIfFalse, pos);
-        self.len() - 3
+    pub fn push_pop_jump_if_false(&mut self, pos: usize) -> usize {
+        self.push_wide_jump(OpCode::PopJumpIfFalse, pos)
+    }
+    pub fn push_pop_jump_if_true(&mut self, pos: usize) -> usize {
+        self.push_wide_jump(OpCode::PopJumpIfTrue, pos)
+    }
+    pub fn push_jump_if_false_or_pop(&mut self, pos: usize) -> usize {
+        self.push_wide_jump(OpCode::JumpIfFalseOrPop, pos)
+    }
+    pub fn push_jump_if_true_or_pop(&mut self, pos: usize) -> usize {
+        self.push_wide_jump(OpCode::JumpIfTrueOrPop, pos)
+    }
+    fn push_wide_jump(&mut self, opcode: OpCode, pos: usize) -> usize {
+        let start = self.len();
+        let end = start + 2 * OpCode::INSTRUCTION_SIZE;
+        let offset = i32::try_from(instr::relative(end, pos)).unwrap();
+        self.push_wide(opcode, u32::from_le_bytes(offset.to_le_bytes()));
+        start
     }
     pub fn patch_jump(&mut self, pos: usize) {
-        let new_pos = u16::try_from(self.len()).unwrap();
-        let new_pos_bytes = new_pos.to_le_bytes();
-        self.bytes[pos + 1] = new_pos_bytes[0];
-        self.bytes[pos + 2] = new_pos_bytes[1];
+        debug_assert_eq!(self.bytes[pos], OpCode::ExtendedArg as u8);
+        let end = pos + 2 * OpCode::INSTRUCTION_SIZE;
+        let offset = i32::try_from(instr::relative(end, self.len())).unwrap();
+        let [low, high, extended_low, extended_high] = offset.to_le_bytes();
+        self.bytes[pos + 1..pos + 3].copy_from_slice(&[extended_low, extended_high]);
+        self.bytes[pos + 4..pos + 6].copy_from_slice(&[low, high]);
     }
+    /// Re-encodes the pool, keeping only the `ExtendedArg` prefixes that are needed.
+    ///
+    /// Handles returned by the jump helpers are invalid afterwards, so this should be done last.
+    pub fn relax(&mut self) {
+        let instrs = self.decode().unwrap();
+        *self = Self::from_instrs(&instrs, std::mem::take(&mut self.constants).into_vec());
+    }
+    /// Appends `other`, merging its constants into this pool's table.
+    ///
+    /// Jumps are relative, so `other` is re-encoded as is apart from its
+    /// `LoadConst` and `Format` indices, which may now need a prefix. A jump to the end of
+    /// `other` lands on whatever gets pushed after it.
+    pub fn append(&mut self, other: Pool) {
+        let mut instrs = other.decode().unwrap();
+        let indices: Vec<u32> = other
+            .constants
+            .into_vec()
+            .into_iter()
+            .map(|value| self.insert_const(value))
+            .collect();
+        for instr in &mut instrs {
+            if let Instr::LoadConst(index) | Instr::Format(index) = instr {
+                *index = indices[*index as usize];
+            }
+        }
+        let fragment = Self::from_instrs(&instrs, vec![]);
+        self.bytes.extend_from_slice(&fragment.bytes);
+    }
+    pub fn decode(&self) -> Result<Vec<Instr>, Error> {
+        self.decode_with_offsets().map(|(instrs, _)| instrs)
+    }
+    fn decode_with_offsets(&self) -> Result<(Vec<Instr>, Vec<usize>), Error> {
+        let mut instrs = Vec::with_capacity(self.len() / OpCode::INSTRUCTION_SIZE);
+        let mut offsets = Vec::with_capacity(instrs.capacity() + 1);
+        let mut prefix = None;
+        let mut start = None;
+        for (head, chunk) in (0..)
+            .step_by(OpCode::INSTRUCTION_SIZE)
+            .zip(self.bytes.chunks(OpCode::INSTRUCTION_SIZE))
+        {
+            let &[op_byte, low, high] = chunk else {
+                return Err(Error::UnexpectedEnd);
+            };
+            if op_byte >= OpCode::LEN as u8 {
+                return Err(Error::InvalidOpCode(op_byte));
+            }
+            let op: OpCode = unsafe { std::mem::transmute(op_byte) };
+            let operand = u16::from_le_bytes([low, high]);
+            if let OpCode::ExtendedArg = op {
+                start.get_or_insert(head);
+                prefix = Some(OpCode::widen(prefix, operand));
+                continue;
+            }
+            let prefix = prefix.take();
+            let target = || {
+                let end = head + OpCode::INSTRUCTION_SIZE;
+                instr::absolute(end, OpCode::offset(prefix, operand)).map(Target)
+            };
+
+            offsets.push(start.take().unwrap_or(head));
+            instrs.push(match op {
+                OpCode::NOP => Instr::Nop,
+                OpCode::Dup => Instr::Dup,
+                OpCode::Pop => Instr::Pop,
+                OpCode::Swap => Instr::Swap,
+                OpCode::Rot3 => Instr::Rot3,
+                OpCode::Over => Instr::Over,
+                OpCode::PopN => Instr::PopN(OpCode::widen(prefix, operand)),
+                OpCode::Print => Instr::Print,
+                OpCode::ToStr => Instr::ToStr,
+                OpCode::ToInt => Instr::ToInt,
+                OpCode::ToFloat => Instr::ToFloat,
+                OpCode::Format => Instr::Format(OpCode::widen(prefix, operand)),
+                OpCode::BinOp => Instr::BinOp(BinOp::try_from(low)?),
+                OpCode::UnaryOp => Instr::UnaryOp(UnaryOp::try_from(low)?),
+                OpCode::LoadConst => Instr::LoadConst(OpCode::widen(prefix, operand)),
+                OpCode::BuildList => Instr::BuildList(OpCode::widen(prefix, operand)),
+                OpCode::Index => Instr::Index,
+                OpCode::StoreIndex => Instr::StoreIndex,
+                OpCode::Len => Instr::Len,
+                OpCode::Append => Instr::Append,
+                OpCode::BuildMap => Instr::BuildMap(OpCode::widen(prefix, operand)),
+                OpCode::GetItem => Instr::GetItem,
+                OpCode::SetItem => Instr::SetItem,
+                OpCode::HasKey => Instr::HasKey,
+                OpCode::Keys => Instr::Keys,
+                OpCode::Jump => Instr::Jump(target()?),
+                OpCode::PopJumpIfFalse => Instr::PopJumpIfFalse(target()?),
+                OpCode::PopJumpIfTrue => Instr::PopJumpIfTrue(target()?),
+                OpCode::JumpIfFalseOrPop => Instr::JumpIfFalseOrPop(target()?),
+                OpCode::JumpIfTrueOrPop => Instr::JumpIfTrueOrPop(target()?),
+                OpCode::ExtendedArg | OpCode::LEN => unreachable!(),
+            });
+        }
+        if start.is_some() {
+            return Err(Error::UnexpectedEnd);
+        }
+        offsets.push(self.len());
+        instr::resolve_targets(&mut instrs, &offsets)?;
+        Ok((instrs, offsets))
+    }
+    /// Encodes `instrs`, prefixing only the operands that don't fit 16 bits.
     #[must_use]
-    pub fn len_u16(&self) -> u16 {
-        u16::try_from(self.len()).unwrap()
+    pub fn from_instrs(instrs: &[Instr], constants: Vec<Value>) -> Self {
+        let wide = Self::prefixes(instrs);
+        let offsets = Self::layout(instrs, &wide);
+        let offset = |index: usize, target: Target| {
+            i32::try_from(instr::relative(offsets[index + 1], offsets[target.0])).unwrap()
+        };
+
+        let mut pool = Self {
+            bytes: Vec::with_capacity(*offsets.last().unwrap()),
+            constants: constants.into(),
+        };
+        for (index, instr) in instrs.iter().enumerate() {
+            match *instr {
+                Instr::Nop => pool.push_zeroed(OpCode::NOP),
+                Instr::Dup => pool.push_zeroed(OpCode::Dup),
+                Instr::Pop => pool.push_zeroed(OpCode::Pop),
+                Instr::Swap => pool.push_zeroed(OpCode::Swap),
+                Instr::Rot3 => pool.push_zeroed(OpCode::Rot3),
+                Instr::Over => pool.push_zeroed(OpCode::Over),
+                Instr::PopN(len) => pool.push_pop_n(len),
+                Instr::Print => pool.push_zeroed(OpCode::Print),
+                Instr::ToStr => pool.push_zeroed(OpCode::ToStr),
+                Instr::ToInt => pool.push_zeroed(OpCode::ToInt),
+                Instr::ToFloat => pool.push_zeroed(OpCode::ToFloat),
+                Instr::Format(index) => pool.push_operand(OpCode::Format, index),
+                Instr::BinOp(binop) => pool.push_binop(binop),
+                Instr::UnaryOp(op) => pool.push_unaryop(op),
+                Instr::LoadConst(index) => pool.push_operand(OpCode::LoadConst, index),
+                Instr::BuildList(len) => pool.push_build_list(len),
+                Instr::Index => pool.push_zeroed(OpCode::Index),
+                Instr::StoreIndex => pool.push_zeroed(OpCode::StoreIndex),
+                Instr::Len => pool.push_zeroed(OpCode::Len),
+                Instr::Append => pool.push_zeroed(OpCode::Append),
+                Instr::BuildMap(len) => pool.push_build_map(len),
+                Instr::GetItem => pool.push_zeroed(OpCode::GetItem),
+                Instr::SetItem => pool.push_zeroed(OpCode::SetItem),
+                Instr::HasKey => pool.push_zeroed(OpCode::HasKey),
+                Instr::Keys => pool.push_zeroed(OpCode::Keys),
+                Instr::Jump(target)
+                | Instr::PopJumpIfFalse(target)
+                | Instr::PopJumpIfTrue(target)
+                | Instr::JumpIfFalseOrPop(target)
+                | Instr::JumpIfTrueOrPop(target) => {
+                    let opcode = match instr {
+                        Instr::Jump(_) => OpCode::Jump,
+                        Instr::PopJumpIfFalse(_) => OpCode::PopJumpIfFalse,
+                        Instr::PopJumpIfTrue(_) => OpCode::PopJumpIfTrue,
+                        Instr::JumpIfFalseOrPop(_) => OpCode::JumpIfFalseOrPop,
+                        _ => OpCode::JumpIfTrueOrPop,
+                    };
+                    let offset = offset(index, target);
+                    if wide[index] {
+                        pool.push_wide(opcode, u32::from_le_bytes(offset.to_le_bytes()));
+                    } else {
+                        pool.push_i16(opcode, i16::try_from(offset).unwrap());
+                    }
+                }
+            }
+        }
+        pool
+    }
+    /// Decides which instructions need an `ExtendedArg`, starting from the
+    /// wide operands and adding jumps whose offset doesn't fit until the
+    /// layout settles. A prefix only ever stretches the distance a jump covers,
+    /// so this terminates.
+    fn prefixes(instrs: &[Instr]) -> Vec<bool> {
+        let mut wide: Vec<bool> = instrs
+            .iter()
+            .map(|instr| match *instr {
+                Instr::LoadConst(operand)
+                | Instr::PopN(operand)
+                | Instr::Format(operand)
+                | Instr::BuildList(operand)
+                | Instr::BuildMap(operand) => u16::try_from(operand).is_err(),
+                _ => false,
+            })
+            .collect();
+        loop {
+            let offsets = Self::layout(instrs, &wide);
+            let mut changed = false;
+            for (index, instr) in instrs.iter().enumerate() {
+                if let Some(target) = instr.target() {
+                    let offset = instr::relative(offsets[index + 1], offsets[target.0]);
+                    if !wide[index] && i16::try_from(offset).is_err() {
+                        wide[index] = true;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return wide;
+            }
+        }
+    }
+    fn layout(instrs: &[Instr], wide: &[bool]) -> Vec<usize> {
+        let mut wide = wide.iter();
+        instr::layout(instrs, |_| {
+            if *wide.next().unwrap() {
+                2 * OpCode::INSTRUCTION_SIZE
+            } else {
+                OpCode::INSTRUCTION_SIZE
+            }
+        })
+    }
+}
+
+impl Assembler for Pool {
+    type Vm = Vm;
+
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+    fn push_nop(&mut self) {
+        self.push_zeroed(OpCode::NOP);
+    }
+    fn push_dup(&mut self) {
+        self.push_zeroed(OpCode::Dup);
+    }
+    fn push_pop(&mut self) {
+        self.push_zeroed(OpCode::Pop);
+    }
+    fn push_swap(&mut self) {
+        self.push_zeroed(OpCode::Swap);
+    }
+    fn push_rot3(&mut self) {
+        self.push_zeroed(OpCode::Rot3);
+    }
+    fn push_over(&mut self) {
+        self.push_zeroed(OpCode::Over);
+    }
+    fn push_pop_n(&mut self, len: usize) {
+        Pool::push_pop_n(self, u32::try_from(len).unwrap());
+    }
+    fn push_print(&mut self) {
+        self.push_zeroed(OpCode::Print);
+    }
+    fn push_to_str(&mut self) {
+        self.push_zeroed(OpCode::ToStr);
+    }
+    fn push_to_int(&mut self) {
+        self.push_zeroed(OpCode::ToInt);
+    }
+    fn push_to_float(&mut self) {
+        self.push_zeroed(OpCode::ToFloat);
+    }
+    fn push_format(&mut self, template: &str) {
+        Pool::push_format(self, template);
+    }
+    fn push_build_list(&mut self, len: usize) {
+        Pool::push_build_list(self, u32::try_from(len).unwrap());
+    }
+    fn push_index(&mut self) {
+        self.push_zeroed(OpCode::Index);
+    }
+    fn push_store_index(&mut self) {
+        self.push_zeroed(OpCode::StoreIndex);
+    }
+    fn push_len(&mut self) {
+        self.push_zeroed(OpCode::Len);
+    }
+    fn push_append(&mut self) {
+        self.push_zeroed(OpCode::Append);
+    }
+    fn push_build_map(&mut self, len: usize) {
+        Pool::push_build_map(self, u32::try_from(len).unwrap());
+    }
+    fn push_get_item(&mut self) {
+        self.push_zeroed(OpCode::GetItem);
+    }
+    fn push_set_item(&mut self) {
+        self.push_zeroed(OpCode::SetItem);
+    }
+    fn push_has_key(&mut self) {
+        self.push_zeroed(OpCode::HasKey);
+    }
+    fn push_keys(&mut self) {
+        self.push_zeroed(OpCode::Keys);
+    }
+    fn push_binop(&mut self, binop: BinOp) {
+        Pool::push_binop(self, binop);
+    }
+    fn push_unaryop(&mut self, op: UnaryOp) {
+        Pool::push_unaryop(self, op);
+    }
+    fn push_const(&mut self, value: Value) -> usize {
+        let index = self.insert_const(value);
+        self.push_operand(OpCode::LoadConst, index);
+        index as usize
+    }
+    fn push_jump(&mut self, pos: usize) -> usize {
+        Pool::push_jump(self, pos)
+    }
+    fn push_pop_jump_if_false(&mut self, pos: usize) -> usize {
+        Pool::push_pop_jump_if_false(self, pos)
+    }
+    fn push_pop_jump_if_true(&mut self, pos: usize) -> usize {
+        Pool::push_pop_jump_if_true(self, pos)
+    }
+    fn push_jump_if_false_or_pop(&mut self, pos: usize) -> usize {
+        Pool::push_jump_if_false_or_pop(self, pos)
+    }
+    fn push_jump_if_true_or_pop(&mut self, pos: usize) -> usize {
+        Pool::push_jump_if_true_or_pop(self, pos)
+    }
+    fn patch_jump(&mut self, jump: usize) {
+        Pool::patch_jump(self, jump);
+    }
+    fn append(&mut self, other: Self) {
+        Pool::append(self, other);
+    }
+    fn constants(&self) -> &[Value] {
+        &self.constants
+    }
+    fn decode(&self) -> Result<Vec<Instr>, Error> {
+        Pool::decode(self)
+    }
+    fn from_instrs(instrs: &[Instr], constants: Vec<Value>) -> Self {
+        Pool::from_instrs(instrs, constants)
     }
 }
 
-impl<'a> Deref for Pool<'a> {
+impl Deref for Pool {
     type Target = [u8];
     fn deref(&self) -> &Self::Target {
         &self.bytes
     }
 }
 
-impl<'a> fmt::Display for Pool<'a> {
+impl fmt::Display for Pool {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fn read(bytes: &[u8], head: usize) -> [u8; 2] {
-            bytes[head..head + 2].try_into().unwrap()
-        }
-        fn read_u16(bytes: &[u8], head: usize) -> u16 {
-            u16::from_le_bytes(read(bytes, head))
-        }
-        let mut head = 0;
-        while head < self.len() {
-            let op_byte = self[head];
-            assert!(op_byte < OpCode::LEN as u8);
-            let op: OpCode = unsafe { std::mem::transmute(op_byte) };
-
-            write!(f, "{head} ")?;
-            head += 1;
-
-            match op {
-                OpCode::LEN => unreachable!(),
-                OpCode::Dup => writeln!(f, "Dup")?,
-                OpCode::NOP => writeln!(f, "Nop")?,
-                OpCode::BinOp => {
-                    let binop_byte = self[head];
-                    let binop: BinOp = unsafe { std::mem::transmute(binop_byte) };
-                    writeln!(f, "BinOp ({binop:?})")?;
-                }
-                OpCode::LoadConst => {
-                    let index = read_u16(self, head);
-                    let constant = &self.constants[index as usize];
-                    writeln!(f, "LoadConst ({constant:?})")?;
-                }
-                OpCode::Jump => {
-                    let jump = read_u16(self, head);
-                    writeln!(f, "Jump ({jump})")?;
-                }
-                OpCode::PopJumpIfFalse => {
-                    let jump = read_u16(self, head);
-                    writeln!(f, "PopJumpIfFalse ({jump})")?;
-                }
-            }
-            head += 2;
+        match self.decode_with_offsets() {
+            Ok((instrs, offsets)) => instr::disassemble(f, &instrs, &offsets, &self.constants),
+            Err(err) => writeln!(f, "{err}"),
         }
-        Ok(())
     }
 }
@@ -2,8 +2,10 @@ use super::{
     bytecode::{OpCode, Pool},
     vm,
 };
-use crate::{BinOp, Value};
-use std::borrow::Cow;
+use crate::{
+    output::{Buffer, Output},
+    BinOp, Error, UnaryOp, Value,
+};
 
 #[test]
 fn test_consts() {
@@ -13,13 +15,13 @@ fn test_consts() {
     pool.push_literal("Hello, World!");
 
     eprintln!("{}", &pool);
-    let stack = vm::create_and_run(&pool);
+    let stack = vm::create_and_run(&pool).unwrap();
     assert_eq!(
         stack,
         vec![
             Value::Int(1),
             Value::Float(1.5),
-            Value::Str(Cow::Borrowed("Hello, World!"))
+            Value::Str("Hello, World!".into())
         ]
     );
 }
@@ -31,12 +33,46 @@ fn test_dup() {
     pool.push_zeroed(OpCode::Dup);
 
     eprintln!("{pool}");
-    let stack = vm::create_and_run(&pool);
+    let stack = vm::create_and_run(&pool).unwrap();
     assert_eq!(
         stack,
         vec![Value::Int(1), Value::Float(2.0), Value::Float(2.0)]
     );
 }
+
+#[test]
+fn test_stack_ops() {
+    let mut pool = Pool::default();
+    for int in 1..=4 {
+        pool.push_literal(int);
+    }
+    pool.push_zeroed(OpCode::Rot3);
+    pool.push_zeroed(OpCode::Swap);
+    pool.push_zeroed(OpCode::Over);
+    pool.push_pop_n(2);
+    pool.push_zeroed(OpCode::Pop);
+
+    eprintln!("{pool}");
+    let stack = vm::create_and_run(&pool).unwrap();
+    assert_eq!(stack, vec![Value::Int(1), Value::Int(4)]);
+}
+
+#[test]
+fn test_print() {
+    let mut pool = Pool::default();
+    pool.push_literal(1.5);
+    pool.push_zeroed(OpCode::Print);
+    pool.push_literal("done");
+    pool.push_zeroed(OpCode::Print);
+
+    let buffer = Buffer::default();
+    let mut vm: vm::Vm = vm::Vm::new(&pool);
+    vm.output = Output::new(buffer.clone());
+    vm.run().unwrap();
+    assert_eq!(buffer.contents(), "1.5\ndone\n");
+    assert!(vm.stack.is_empty());
+}
+
 #[test]
 fn test_binops() {
     let mut pool = Pool::default();
@@ -48,11 +84,23 @@ fn test_binops() {
     pool.push_literal("Hello, ");
     pool.push_binop(BinOp::Mul);
 
-    let stack = vm::create_and_run(&pool);
-    assert_eq!(
-        stack,
-        vec![Value::Str(Cow::Borrowed("Hello, Hello, Hello, "))]
-    );
+    let stack = vm::create_and_run(&pool).unwrap();
+    assert_eq!(stack, vec![Value::Str("Hello, Hello, Hello, ".into())]);
+}
+#[test]
+fn test_unaryops() {
+    let mut pool = Pool::default();
+    pool.push_literal(5);
+    pool.push_unaryop(UnaryOp::Neg);
+    pool.push_literal(0);
+    pool.push_unaryop(UnaryOp::BitNot);
+    pool.push_literal("");
+    pool.push_unaryop(UnaryOp::Not);
+
+    eprintln!("{pool}");
+    let stack = vm::create_and_run(&pool).unwrap();
+    assert_eq!(stack, vec![Value::Int(-5), Value::Int(-1), Value::Int(1)]);
+    assert!(pool.to_string().contains("UnaryOp (BitNot)"));
 }
 
 #[test]
@@ -66,7 +114,7 @@ fn test_jump() {
     pool.push_literal(4);
 
     eprintln!("{pool}");
-    let stack = vm::create_and_run(&pool);
+    let stack = vm::create_and_run(&pool).unwrap();
     assert_eq!(stack, vec![Value::Int(1), Value::Int(4)]);
 }
 
@@ -75,10 +123,133 @@ fn test_pop_jump_if_false() {
     let mut pool = Pool::default();
     pool.push_literal(1);
     pool.push_literal(0);
-    let start = pool.len_u16();
+    let start = pool.len();
     pool.push_pop_jump_if_false(start);
 
     eprintln!("{pool}");
-    let stack = vm::create_and_run(&pool);
+    let stack = vm::create_and_run(&pool).unwrap();
     assert_eq!(stack, vec![]);
 }
+
+#[test]
+fn test_pop_jump_if_true() {
+    let mut pool = Pool::default();
+    pool.push_literal("");
+    let jump = pool.push_pop_jump_if_true(0);
+    pool.push_literal(1);
+    pool.patch_jump(jump);
+    pool.push_literal("hello");
+    let jump = pool.push_pop_jump_if_true(0);
+    pool.push_literal(2);
+    pool.patch_jump(jump);
+
+    eprintln!("{pool}");
+    let stack = vm::create_and_run(&pool).unwrap();
+    assert_eq!(stack, vec![Value::Int(1)]);
+}
+
+#[test]
+fn test_short_circuit() {
+    // `0 and 1` stays at `0`, then `0 or 2` becomes `2`.
+    let mut pool = Pool::default();
+    pool.push_literal(0);
+    let and = pool.push_jump_if_false_or_pop(0);
+    pool.push_literal(1);
+    pool.patch_jump(and);
+    let or = pool.push_jump_if_true_or_pop(0);
+    pool.push_literal(2);
+    pool.patch_jump(or);
+    pool.relax();
+
+    eprintln!("{pool}");
+    let stack = vm::create_and_run(&pool).unwrap();
+    assert_eq!(stack, vec![Value::Int(2)]);
+}
+
+#[test]
+fn test_errors() {
+    let mut pool = Pool::default();
+    pool.push_literal(1);
+    pool.push_binop(BinOp::Add);
+    assert_eq!(vm::create_and_run(&pool), Err(Error::StackUnderflow));
+
+    let mut pool = Pool::default();
+    pool.push_literal("1");
+    pool.push_literal(1);
+    pool.push_binop(BinOp::Add);
+    assert_eq!(
+        vm::create_and_run(&pool),
+        Err(Error::UnsupportedOperands {
+            op: BinOp::Add,
+            lhs: "str",
+            rhs: "int"
+        })
+    );
+}
+
+#[test]
+fn test_invalid_operators() {
+    let mut pool = Pool::default();
+    pool.push_literal(1);
+    pool.push_literal(2);
+    pool.push(OpCode::BinOp, [BinOp::COUNT, 0]);
+    assert_eq!(pool.decode(), Err(Error::InvalidOperator(BinOp::COUNT)));
+    assert_eq!(
+        vm::create_and_run(&pool),
+        Err(Error::InvalidOperator(BinOp::COUNT))
+    );
+
+    let mut pool = Pool::default();
+    pool.push_literal(1);
+    pool.push(OpCode::UnaryOp, [u8::MAX, 0]);
+    assert_eq!(
+        vm::create_and_run(&pool),
+        Err(Error::InvalidOperator(u8::MAX))
+    );
+}
+
+#[test]
+fn test_extended_arg_constants() {
+    let mut pool = Pool::default();
+    for int in 0..70_000 {
+        pool.insert_const(Value::Int(int));
+    }
+    pool.push_literal(1);
+    pool.push_literal(70_000);
+    assert_eq!(pool.bytes[3], OpCode::ExtendedArg as u8);
+    assert_eq!(pool.len(), 9);
+
+    let stack = vm::create_and_run(&pool).unwrap();
+    assert_eq!(stack, vec![Value::Int(1), Value::Int(70_000)]);
+}
+
+#[test]
+fn test_extended_arg_jumps() {
+    use crate::Assembler;
+
+    let has_prefix = |pool: &Pool, at: usize| pool.bytes[at] == OpCode::ExtendedArg as u8;
+    for (nops, prefixed) in [(1, false), (20_000, true)] {
+        let mut pool = Pool::default();
+        pool.push_literal(0);
+        pool.push_if(|pool| {
+            for _ in 0..nops {
+                pool.push_nop();
+            }
+        });
+        pool.push_literal("end");
+        assert!(has_prefix(&pool, 3));
+
+        pool.relax();
+        assert_eq!(has_prefix(&pool, 3), prefixed, "{nops} nops");
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Str("end".into())]);
+    }
+}
+
+#[test]
+fn test_dangling_extended_arg() {
+    let mut pool = Pool::default();
+    pool.push_literal(1);
+    pool.push_u16(OpCode::ExtendedArg, 1);
+    assert_eq!(pool.decode(), Err(Error::UnexpectedEnd));
+}
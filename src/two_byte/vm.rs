@@ -1,90 +1,280 @@
 use super::bytecode::{OpCode, Pool};
-use crate::Value;
+use crate::{
+    format::Template, instr, output::Output, ArithmeticMode, BinOp, Error, Machine, Slot, UnaryOp,
+    Value,
+};
+use std::rc::Rc;
 
-#[must_use]
-pub fn create_and_run<'a>(pool: &'a Pool<'a>) -> Vec<Value<'a>> {
-    let mut vm = Vm::new(pool);
-    vm.run();
-    vm.stack
+pub fn create_and_run(pool: &Pool) -> Result<Vec<Value>, Error> {
+    let mut vm: Vm = Vm::new(pool);
+    vm.run()?;
+    Ok(vm.stack)
 }
 
+/// Runs on `Value`s by default, or on any other `Slot` such as `packed::Packed`.
 #[derive(Debug)]
-pub struct Vm<'a> {
-    pub bytes: &'a [u8],
-    pub constants: &'a [Value<'a>],
+pub struct Vm<V: Slot = Value> {
+    pub bytes: Rc<[u8]>,
+    pub constants: Rc<[V]>,
     pub head: usize,
-    pub stack: Vec<Value<'a>>,
+    pub stack: Vec<V>,
+    pub mode: ArithmeticMode,
+    /// Where `Print` writes.
+    pub output: Output,
+    /// The operand bits collected from `ExtendedArg`s for the next instruction.
+    pub prefix: Option<u32>,
 }
 
-impl<'a> Vm<'a> {
+impl<V: Slot> Vm<V> {
     #[must_use]
-    pub fn new(pool: &'a Pool<'a>) -> Self {
+    pub fn new(pool: &Pool) -> Self {
         Self {
-            bytes: &pool.bytes,
-            constants: &pool.constants,
+            bytes: pool.bytes.as_slice().into(),
+            constants: pool.constants.iter().cloned().map(V::from_value).collect(),
             head: 0,
             stack: vec![],
+            mode: ArithmeticMode::default(),
+            output: Output::default(),
+            prefix: None,
         }
     }
-    pub fn run(&mut self) {
-        eprintln!("{:?}", self.constants);
+    pub fn run(&mut self) -> Result<(), Error> {
         while self.head < self.bytes.len() {
-            self.run_next();
+            self.run_next()?;
         }
+        Ok(())
     }
-    pub fn run_next(&mut self) {
+    #[allow(clippy::too_many_lines)]
+    pub fn run_next(&mut self) -> Result<(), Error> {
         let op_code_byte = self.bytes[self.head];
         self.head += 1;
 
-        assert!(op_code_byte < OpCode::LEN as u8);
-        let op_code = unsafe { std::mem::transmute(op_code_byte) };
+        if op_code_byte >= OpCode::LEN as u8 {
+            return Err(Error::InvalidOpCode(op_code_byte));
+        }
+        let op_code: OpCode = unsafe { std::mem::transmute(op_code_byte) };
+        let prefix = self.prefix.take();
 
         match op_code {
             OpCode::NOP => (),
             OpCode::Dup => {
-                if let Some(last) = self.stack.last() {
-                    self.stack.push(last.clone());
-                }
+                let last = self.stack.last().ok_or(Error::StackUnderflow)?;
+                self.stack.push(last.clone());
+            }
+            OpCode::Pop => {
+                self.pop()?;
+            }
+            OpCode::Swap => self.top_mut(2)?.swap(0, 1),
+            OpCode::Rot3 => self.top_mut(3)?.rotate_right(1),
+            OpCode::Over => {
+                let second = self.top_mut(2)?[0].clone();
+                self.stack.push(second);
+            }
+            OpCode::PopN => {
+                let len = OpCode::widen(prefix, self.read_u16()) as usize;
+                self.split_top(len)?;
+            }
+            OpCode::Print => {
+                let value = self.pop()?;
+                self.output.print(&value.into_value())?;
+            }
+            OpCode::ToStr => {
+                let value = self.pop()?;
+                self.stack.push(V::to_str(value));
+            }
+            OpCode::ToInt => {
+                let value = self.pop()?;
+                self.stack.push(V::to_int(value)?);
+            }
+            OpCode::ToFloat => {
+                let value = self.pop()?;
+                self.stack.push(V::to_float(value)?);
+            }
+            OpCode::Format => {
+                let index = OpCode::widen(prefix, self.read_u16()) as usize;
+                let template = self
+                    .constants
+                    .get(index)
+                    .ok_or(Error::InvalidConstant(index))?;
+                let template = Template::try_from(&template.clone().into_value())?;
+                let args = self.split_top(template.holes())?;
+                let rendered = template.render(args.into_iter().map(Slot::into_value));
+                self.stack.push(V::from_value(rendered));
             }
             OpCode::LoadConst => {
-                let index = self.read_u16();
-                let constant = &self.constants[index as usize];
-                self.stack.push(constant.clone());
+                let index = OpCode::widen(prefix, self.read_u16()) as usize;
+                let constant = self
+                    .constants
+                    .get(index)
+                    .ok_or(Error::InvalidConstant(index))?;
+                self.stack.push(V::load_const(constant));
+            }
+            OpCode::BuildList => {
+                let len = OpCode::widen(prefix, self.read_u16()) as usize;
+                let items = self.split_top(len)?;
+                self.stack.push(V::build_list(items));
+            }
+            OpCode::Index => {
+                let index = self.pop()?;
+                let list = self.pop()?;
+                self.stack.push(V::index(list, index)?);
+            }
+            OpCode::StoreIndex => {
+                let value = self.pop()?;
+                let index = self.pop()?;
+                let list = self.pop()?;
+                V::store_index(list, index, value)?;
+            }
+            OpCode::Len => {
+                let list = self.pop()?;
+                self.stack.push(V::length(list)?);
+            }
+            OpCode::Append => {
+                let value = self.pop()?;
+                V::append(self.stack.last().ok_or(Error::StackUnderflow)?, value)?;
+            }
+            OpCode::BuildMap => {
+                let len = OpCode::widen(prefix, self.read_u16()) as usize;
+                let items = self.split_top(len.checked_mul(2).ok_or(Error::StackUnderflow)?)?;
+                self.stack.push(V::build_map(items)?);
+            }
+            OpCode::GetItem => {
+                let key = self.pop()?;
+                let map = self.pop()?;
+                self.stack.push(V::get_item(map, key)?);
+            }
+            OpCode::SetItem => {
+                let value = self.pop()?;
+                let key = self.pop()?;
+                let map = self.pop()?;
+                V::set_item(map, key, value)?;
+            }
+            OpCode::HasKey => {
+                let key = self.pop()?;
+                let map = self.pop()?;
+                self.stack.push(V::has_key(map, key)?);
+            }
+            OpCode::Keys => {
+                let map = self.pop()?;
+                self.stack.push(V::keys(map)?);
             }
             OpCode::BinOp => {
-                let binop_byte = self.bytes[self.head];
-                let binop = unsafe { std::mem::transmute(binop_byte) };
+                let binop = BinOp::try_from(self.bytes[self.head])?;
 
-                let rhs = self.stack.pop().unwrap();
-                let lhs = self.stack.pop().unwrap();
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
 
-                let new_val = Value::run_binop(lhs, rhs, binop);
+                let new_val = V::run_binop(lhs, rhs, binop, self.mode)?;
                 self.stack.push(new_val);
             }
+            OpCode::UnaryOp => {
+                let op = UnaryOp::try_from(self.bytes[self.head])?;
+
+                let operand = self.pop()?;
+                self.stack.push(V::run_unaryop(operand, op, self.mode)?);
+            }
             OpCode::Jump => {
-                let location = self.read_u16();
-                self.head = location as usize;
-                return;
+                self.head = self.read_target(prefix)?;
+                return Ok(());
             }
             OpCode::PopJumpIfFalse => {
-                let location = self.read_u16();
+                let location = self.read_target(prefix)?;
+
+                let top = self.pop()?;
 
-                let top = self.stack.pop().unwrap();
+                if !top.is_truthy() {
+                    self.head = location;
+                    return Ok(());
+                }
+            }
+            OpCode::PopJumpIfTrue => {
+                let location = self.read_target(prefix)?;
+
+                let top = self.pop()?;
+
+                if top.is_truthy() {
+                    self.head = location;
+                    return Ok(());
+                }
+            }
+            OpCode::JumpIfFalseOrPop => {
+                let location = self.read_target(prefix)?;
 
-                if !bool::from(&top) {
-                    self.head = location as usize;
-                    return;
+                let top = self.stack.last().ok_or(Error::StackUnderflow)?;
+
+                if !top.is_truthy() {
+                    self.head = location;
+                    return Ok(());
+                }
+                self.stack.pop();
+            }
+            OpCode::JumpIfTrueOrPop => {
+                let location = self.read_target(prefix)?;
+
+                let top = self.stack.last().ok_or(Error::StackUnderflow)?;
+
+                if top.is_truthy() {
+                    self.head = location;
+                    return Ok(());
                 }
+                self.stack.pop();
+            }
+            OpCode::ExtendedArg => {
+                self.prefix = Some(OpCode::widen(prefix, self.read_u16()));
             }
             OpCode::LEN => unreachable!(),
         }
 
         self.head += 2;
+        Ok(())
+    }
+    pub fn pop(&mut self) -> Result<V, Error> {
+        self.stack.pop().ok_or(Error::StackUnderflow)
+    }
+    /// Pops the top `len` values, keeping them in push order.
+    pub fn split_top(&mut self, len: usize) -> Result<Vec<V>, Error> {
+        let at = self
+            .stack
+            .len()
+            .checked_sub(len)
+            .ok_or(Error::StackUnderflow)?;
+        Ok(self.stack.split_off(at))
+    }
+    /// The top `len` values, in push order.
+    pub fn top_mut(&mut self, len: usize) -> Result<&mut [V], Error> {
+        let at = self
+            .stack
+            .len()
+            .checked_sub(len)
+            .ok_or(Error::StackUnderflow)?;
+        Ok(&mut self.stack[at..])
     }
     pub fn read_u16(&mut self) -> u16 {
         u16::from_le_bytes(self.read_bytes())
     }
+    /// Resolves the relative operand at `head`, widened by `prefix`, against the end of the instruction.
+    pub fn read_target(&mut self, prefix: Option<u32>) -> Result<usize, Error> {
+        let offset = OpCode::offset(prefix, self.read_u16());
+        instr::absolute(self.head + 2, offset)
+    }
     pub fn read_bytes(&mut self) -> [u8; 2] {
         [self.bytes[self.head], self.bytes[self.head + 1]]
     }
 }
+
+impl<V: Slot> Machine for Vm<V> {
+    type Pool = Pool;
+
+    fn new(pool: &Pool) -> Self {
+        Vm::new(pool)
+    }
+    fn is_finished(&self) -> bool {
+        self.head >= self.bytes.len()
+    }
+    fn run_next(&mut self) -> Result<(), Error> {
+        Vm::run_next(self)
+    }
+    fn into_stack(self) -> Vec<Value> {
+        self.stack.into_iter().map(Slot::into_value).collect()
+    }
+}
@@ -0,0 +1,38 @@
+use crate::Error;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// Negates an int or a float.
+    Neg = 0,
+    /// Pushes `1` for a falsy operand and `0` for a truthy one.
+    Not,
+    /// Flips every bit of an int.
+    BitNot,
+}
+
+impl UnaryOp {
+    pub const COUNT: u8 = Self::BitNot as u8 + 1;
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Neg => "Neg",
+            Self::Not => "Not",
+            Self::BitNot => "BitNot",
+        }
+    }
+}
+
+impl TryFrom<u8> for UnaryOp {
+    type Error = Error;
+
+    fn try_from(byte: u8) -> Result<Self, Error> {
+        if byte < Self::COUNT {
+            // SAFETY: `UnaryOp` is `repr(u8)` with contiguous discriminants below `COUNT`.
+            Ok(unsafe { std::mem::transmute::<u8, UnaryOp>(byte) })
+        } else {
+            Err(Error::InvalidOperator(byte))
+        }
+    }
+}
@@ -1,96 +1,788 @@
-use crate::BinOp;
-use std::borrow::Cow;
+use crate::{
+    bigint::BigInt,
+    map::{Key, Map},
+    ArithmeticMode, BinOp, Error, UnaryOp,
+};
+use std::{cell::RefCell, cmp::Ordering, fmt, ptr, rc::Rc};
 
+/// Strings are reference counted so that loading a constant only bumps a count,
+/// and pools intern their string constants so that equal ones share an allocation.
+///
+/// Lists and maps are shared and mutable: every copy of one refers to the same items.
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
-pub enum Value<'a> {
+pub enum Value {
     Int(i64),
+    /// Only ever holds integers outside the `i64` range, smaller results demote to `Int`.
+    BigInt(Rc<BigInt>),
     Float(f64),
-    Str(Cow<'a, str>),
+    Str(Rc<str>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<Map>>),
 }
 
-impl<'a> Value<'a> {
-    #[must_use]
-    pub fn run_binop(lhs: Self, rhs: Self, op: BinOp) -> Self {
+impl Value {
+    /// Integer overflow is handled according to `mode`.
+    pub fn run_binop(lhs: Self, rhs: Self, op: BinOp, mode: ArithmeticMode) -> Result<Self, Error> {
+        let (lhs, rhs) = match (lhs, rhs) {
+            (Self::BigInt(lhs), Self::Int(rhs)) => {
+                return Self::run_big_binop(&lhs, &rhs.into(), op)
+            }
+            (Self::Int(lhs), Self::BigInt(rhs)) => {
+                return Self::run_big_binop(&lhs.into(), &rhs, op)
+            }
+            (Self::BigInt(lhs), Self::BigInt(rhs)) => return Self::run_big_binop(&lhs, &rhs, op),
+            (Self::BigInt(lhs), rhs @ Self::Float(_)) => (Self::Float(lhs.to_f64()), rhs),
+            (lhs @ Self::Float(_), Self::BigInt(rhs)) => (lhs, Self::Float(rhs.to_f64())),
+            operands => operands,
+        };
         match op {
-            BinOp::Add => Self::add(lhs, rhs),
-            BinOp::Sub => Self::sub(lhs, rhs),
-            BinOp::Mul => Self::mul(lhs, rhs),
+            BinOp::Add => Self::add(lhs, rhs, mode),
+            BinOp::Sub => Self::sub(lhs, rhs, mode),
+            BinOp::Mul => Self::mul(lhs, rhs, mode),
+            BinOp::Div => Self::div(lhs, rhs, mode),
+            BinOp::Mod => Self::rem(lhs, rhs),
+            BinOp::Pow => Self::pow(lhs, rhs, mode),
 
-            _ => todo!("{op:?}"),
+            BinOp::LE | BinOp::LT | BinOp::GE | BinOp::GT | BinOp::Eq | BinOp::Ne => {
+                Self::compare(&lhs, &rhs, op)
+            }
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr => {
+                Self::bitwise(&lhs, &rhs, op, mode)
+            }
+        }
+    }
+    #[allow(clippy::cast_precision_loss)]
+    pub fn run_unaryop(operand: &Self, op: UnaryOp, mode: ArithmeticMode) -> Result<Self, Error> {
+        match (op, operand) {
+            (UnaryOp::Neg, &Self::Int(int)) => {
+                mode.resolve(int.checked_neg(), || -&BigInt::from(int))
+            }
+            (UnaryOp::Neg, Self::BigInt(big)) => Ok(Self::big(-&**big)),
+            (UnaryOp::Neg, Self::Float(float)) => Ok(Self::Float(-float)),
+            (UnaryOp::Not, _) => Ok(Self::Int(i64::from(!operand.is_truthy()))),
+            (UnaryOp::BitNot, Self::Int(int)) => Ok(Self::Int(!int)),
+            (UnaryOp::BitNot, Self::BigInt(big)) => Ok(Self::big(!&**big)),
+            _ => Err(Error::UnsupportedOperand {
+                op: op.name(),
+                operand: operand.type_name(),
+            }),
+        }
+    }
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Int(_) | Self::BigInt(_) => "int",
+            Self::Float(_) => "float",
+            Self::Str(_) => "str",
+            Self::List(_) => "list",
+            Self::Map(_) => "map",
+        }
+    }
+    /// Whether conditional jumps treat the value as true.
+    ///
+    /// Numbers are true when nonzero, so `NaN` is true and `-0.0` is false.
+    /// A `BigInt` is never zero, so it is always true. Strings, lists and maps
+    /// are true when they aren't empty.
+    #[must_use]
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Int(int) => *int != 0,
+            Self::BigInt(_) => true,
+            Self::Float(float) => *float != 0.0,
+            Self::Str(str) => !str.is_empty(),
+            Self::List(items) => !items.borrow().is_empty(),
+            Self::Map(entries) => !entries.borrow().is_empty(),
+        }
+    }
+    /// The `Display` of the value, so strings are left as they are.
+    #[must_use]
+    pub fn to_str(&self) -> Self {
+        match self {
+            Self::Str(_) => self.clone(),
+            _ => Self::Str(self.to_string().into()),
+        }
+    }
+    /// Truncates floats toward zero and parses strings as optionally signed
+    /// decimal digits, ignoring surrounding whitespace.
+    pub fn to_int(&self) -> Result<Self, Error> {
+        match self {
+            Self::Int(_) | Self::BigInt(_) => Ok(self.clone()),
+            Self::Float(float) => BigInt::from_f64(*float)
+                .map(Self::big)
+                .ok_or(Error::NonFinite(*float)),
+            Self::Str(str) => {
+                let text = str.trim();
+                match text.parse() {
+                    Ok(int) => Ok(Self::Int(int)),
+                    Err(_) => text.parse().map(Self::big).map_err(|_| Error::Parse {
+                        text: str.clone(),
+                        into: "int",
+                    }),
+                }
+            }
+            _ => Err(Error::UnsupportedOperand {
+                op: "ToInt",
+                operand: self.type_name(),
+            }),
         }
     }
+    /// Parses strings the way Rust does, so `"inf"` and `"nan"` are accepted.
     #[allow(clippy::cast_precision_loss)]
-    fn add(lhs: Self, rhs: Self) -> Self {
-        match (lhs, rhs) {
-            (Self::Int(lhs), Self::Int(rhs)) => Self::Int(lhs + rhs),
+    pub fn to_float(&self) -> Result<Self, Error> {
+        match self {
+            Self::Int(int) => Ok(Self::Float(*int as f64)),
+            Self::BigInt(big) => Ok(Self::Float(big.to_f64())),
+            Self::Float(_) => Ok(self.clone()),
+            Self::Str(str) => str
+                .trim()
+                .parse()
+                .map(Self::Float)
+                .map_err(|_| Error::Parse {
+                    text: str.clone(),
+                    into: "float",
+                }),
+            _ => Err(Error::UnsupportedOperand {
+                op: "ToFloat",
+                operand: self.type_name(),
+            }),
+        }
+    }
+    /// Quotes strings, so that they stand out inside lists and maps.
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(str) => write!(f, "{str:?}"),
+            _ => write!(f, "{self}"),
+        }
+    }
+    /// Demotes `big` to an `Int` when it fits.
+    #[must_use]
+    pub fn big(big: BigInt) -> Self {
+        match big.to_i64() {
+            Some(int) => Self::Int(int),
+            None => Self::BigInt(Rc::new(big)),
+        }
+    }
+    /// Widens an `Int` or `BigInt`, anything else is zero.
+    fn to_big(&self) -> BigInt {
+        match self {
+            Self::Int(int) => BigInt::from(*int),
+            Self::BigInt(big) => (**big).clone(),
+            _ => BigInt::default(),
+        }
+    }
+    /// A copy sharing no list or map with `self`, so that loading a constant
+    /// never hands out the one stored in the pool.
+    #[must_use]
+    pub fn deep_copy(&self) -> Self {
+        match self {
+            Self::List(items) => Self::list(items.borrow().iter().map(Self::deep_copy).collect()),
+            Self::Map(entries) => Self::map(
+                entries
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.deep_copy()))
+                    .collect(),
+            ),
+            _ => self.clone(),
+        }
+    }
+    #[must_use]
+    pub fn list(items: Vec<Value>) -> Self {
+        Self::List(Rc::new(RefCell::new(items)))
+    }
+    pub fn index(list: &Self, index: &Self) -> Result<Self, Error> {
+        let items = Self::items(list, "Index")?.borrow();
+        let index = Self::list_index(index, items.len(), "Index")?;
+        Ok(items[index].clone())
+    }
+    pub fn store_index(list: &Self, index: &Self, value: Self) -> Result<(), Error> {
+        let items = Self::items(list, "StoreIndex")?;
+        Self::check_acyclic(items, &value, "StoreIndex")?;
+        let mut items = items.borrow_mut();
+        let index = Self::list_index(index, items.len(), "StoreIndex")?;
+        items[index] = value;
+        Ok(())
+    }
+    pub fn append(list: &Self, value: Self) -> Result<(), Error> {
+        let items = Self::items(list, "Append")?;
+        Self::check_acyclic(items, &value, "Append")?;
+        items.borrow_mut().push(value);
+        Ok(())
+    }
+    /// Storing `value` into `container` must not make it hold itself, since
+    /// printing, copying and comparing it would then never finish.
+    fn check_acyclic<T>(
+        container: &RefCell<T>,
+        value: &Self,
+        op: &'static str,
+    ) -> Result<(), Error> {
+        if value.reaches(ptr::from_ref(container).cast()) {
+            return Err(Error::Cycle(op));
+        }
+        Ok(())
+    }
+    /// Whether `self` is the list or map at `target` or holds it at any depth.
+    fn reaches(&self, target: *const ()) -> bool {
+        match self {
+            Self::List(items) => {
+                ptr::eq(Rc::as_ptr(items).cast(), target)
+                    || items.borrow().iter().any(|item| item.reaches(target))
+            }
+            Self::Map(entries) => {
+                ptr::eq(Rc::as_ptr(entries).cast(), target)
+                    || entries
+                        .borrow()
+                        .iter()
+                        .any(|(_, value)| value.reaches(target))
+            }
+            _ => false,
+        }
+    }
+    pub fn length(value: &Self) -> Result<Self, Error> {
+        let len = match value {
+            Self::Map(entries) => entries.borrow().len(),
+            _ => Self::items(value, "Len")?.borrow().len(),
+        };
+        Ok(Self::Int(
+            i64::try_from(len).map_err(|_| Error::IntegerOverflow)?,
+        ))
+    }
+    #[must_use]
+    pub fn map(entries: Map) -> Self {
+        Self::Map(Rc::new(RefCell::new(entries)))
+    }
+    /// Builds a map from alternating keys and values, later duplicates win.
+    pub fn build_map(items: Vec<Value>) -> Result<Self, Error> {
+        let mut entries = Map::default();
+        let mut items = items.into_iter();
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+            entries.insert(Key::try_from(&key)?, value);
+        }
+        Ok(Self::map(entries))
+    }
+    pub fn get_item(map: &Self, key: &Self) -> Result<Self, Error> {
+        let key = Key::try_from(key)?;
+        let entries = Self::entries(map, "GetItem")?.borrow();
+        entries.get(&key).cloned().ok_or(Error::KeyNotFound(key))
+    }
+    pub fn set_item(map: &Self, key: &Self, value: Self) -> Result<(), Error> {
+        let key = Key::try_from(key)?;
+        let entries = Self::entries(map, "SetItem")?;
+        Self::check_acyclic(entries, &value, "SetItem")?;
+        entries.borrow_mut().insert(key, value);
+        Ok(())
+    }
+    pub fn has_key(map: &Self, key: &Self) -> Result<Self, Error> {
+        let key = Key::try_from(key)?;
+        let entries = Self::entries(map, "HasKey")?.borrow();
+        Ok(Self::Int(i64::from(entries.contains_key(&key))))
+    }
+    /// The keys of `map` as a new list, in insertion order.
+    pub fn keys(map: &Self) -> Result<Self, Error> {
+        let entries = Self::entries(map, "Keys")?.borrow();
+        Ok(Self::list(
+            entries.keys().cloned().map(Value::from).collect(),
+        ))
+    }
+    fn entries<'v>(map: &'v Self, op: &'static str) -> Result<&'v RefCell<Map>, Error> {
+        match map {
+            Self::Map(entries) => Ok(entries),
+            _ => Err(Error::UnsupportedOperand {
+                op,
+                operand: map.type_name(),
+            }),
+        }
+    }
+    fn items<'v>(list: &'v Self, op: &'static str) -> Result<&'v RefCell<Vec<Value>>, Error> {
+        match list {
+            Self::List(items) => Ok(items),
+            _ => Err(Error::UnsupportedOperand {
+                op,
+                operand: list.type_name(),
+            }),
+        }
+    }
+    /// Indices are never negative, anything outside `0..len` is out of bounds.
+    fn list_index(index: &Self, len: usize, op: &'static str) -> Result<usize, Error> {
+        let Self::Int(index) = *index else {
+            return Err(Error::UnsupportedOperand {
+                op,
+                operand: index.type_name(),
+            });
+        };
+        usize::try_from(index)
+            .ok()
+            .filter(|index| *index < len)
+            .ok_or(Error::IndexOutOfBounds { index, len })
+    }
+    /// The longest list or string that repetition builds, in items or bytes.
+    pub const MAX_REPEAT_LEN: usize = 1 << 24;
+    /// Checks the length of `len` repeated `count` times against `MAX_REPEAT_LEN`.
+    ///
+    /// Repeating nothing any number of times is nothing, so that counts as zero.
+    fn repeat_count(len: usize, count: i64) -> Result<usize, Error> {
+        if len == 0 {
+            return Ok(0);
+        }
+        let count = usize::try_from(count).unwrap_or(0);
+        len.checked_mul(count)
+            .filter(|&total| total <= Self::MAX_REPEAT_LEN)
+            .ok_or(Error::SizeLimit(Self::MAX_REPEAT_LEN))?;
+        Ok(count)
+    }
+    fn repeat(items: &[Value], count: i64) -> Result<Self, Error> {
+        let count = Self::repeat_count(items.len(), count)?;
+        let mut repeated = Vec::with_capacity(items.len() * count);
+        for _ in 0..count {
+            repeated.extend_from_slice(items);
+        }
+        Ok(Self::list(repeated))
+    }
+    #[allow(clippy::cast_precision_loss)]
+    fn add(lhs: Self, rhs: Self, mode: ArithmeticMode) -> Result<Self, Error> {
+        Ok(match (lhs, rhs) {
+            (Self::Int(lhs), Self::Int(rhs)) => {
+                mode.resolve(lhs.checked_add(rhs), || &BigInt::from(lhs) + &rhs.into())?
+            }
             (Self::Float(lhs), Self::Float(rhs)) => Self::Float(lhs + rhs),
             (Self::Float(lhs), Self::Int(rhs)) => Self::Float(lhs + rhs as f64),
             (Self::Int(lhs), Self::Float(rhs)) => Self::Float(lhs as f64 + rhs),
-            (Self::Str(lhs), Self::Str(rhs)) => {
-                Self::Str(Cow::Owned(lhs.into_owned() + rhs.as_ref()))
+            (Self::Str(lhs), Self::Str(rhs)) => Self::Str([&*lhs, &*rhs].concat().into()),
+            (Self::List(lhs), Self::List(rhs)) => {
+                Self::list([&lhs.borrow()[..], &rhs.borrow()[..]].concat())
             }
-            (lhs, rhs) => todo!("{lhs:?} - {rhs:?}"),
-        }
+            (lhs, rhs) => return Err(Error::unsupported(BinOp::Add, &lhs, &rhs)),
+        })
     }
     #[allow(clippy::cast_precision_loss)]
-    fn sub(lhs: Self, rhs: Self) -> Self {
-        match (lhs, rhs) {
-            (Self::Int(lhs), Self::Int(rhs)) => Self::Int(lhs - rhs),
+    fn sub(lhs: Self, rhs: Self, mode: ArithmeticMode) -> Result<Self, Error> {
+        Ok(match (lhs, rhs) {
+            (Self::Int(lhs), Self::Int(rhs)) => {
+                mode.resolve(lhs.checked_sub(rhs), || &BigInt::from(lhs) - &rhs.into())?
+            }
             (Self::Int(lhs), Self::Float(rhs)) => Self::Float(lhs as f64 - rhs),
             (Self::Float(lhs), Self::Int(rhs)) => Self::Float(lhs - rhs as f64),
             (Self::Float(lhs), Self::Float(rhs)) => Self::Float(lhs - rhs),
-            (lhs, rhs) => todo!("{lhs:?} - {rhs:?}"),
-        }
+            (lhs, rhs) => return Err(Error::unsupported(BinOp::Sub, &lhs, &rhs)),
+        })
     }
 
     #[allow(clippy::cast_precision_loss)]
-    #[allow(clippy::cast_possible_truncation)]
-    #[allow(clippy::cast_sign_loss)]
-    fn mul(lhs: Self, rhs: Self) -> Self {
-        match (lhs, rhs) {
-            (Self::Int(lhs), Self::Int(rhs)) => Self::Int(lhs * rhs),
+    fn mul(lhs: Self, rhs: Self, mode: ArithmeticMode) -> Result<Self, Error> {
+        Ok(match (lhs, rhs) {
+            (Self::Int(lhs), Self::Int(rhs)) => {
+                mode.resolve(lhs.checked_mul(rhs), || &BigInt::from(lhs) * &rhs.into())?
+            }
             (Self::Int(lhs), Self::Float(rhs)) => Self::Float(lhs as f64 * rhs),
             (Self::Float(lhs), Self::Int(rhs)) => Self::Float(lhs * rhs as f64),
             (Self::Float(lhs), Self::Float(rhs)) => Self::Float(lhs * rhs),
             (Self::Str(str), Self::Int(int)) | (Self::Int(int), Self::Str(str)) => {
-                Self::Str(Cow::Owned(str.repeat(int as usize)))
+                Self::Str(str.repeat(Self::repeat_count(str.len(), int)?).into())
+            }
+            (Self::List(items), Self::Int(int)) | (Self::Int(int), Self::List(items)) => {
+                Self::repeat(&items.borrow(), int)?
             }
 
-            (lhs, rhs) => todo!("{lhs:?} - {rhs:?}"),
+            (lhs, rhs) => return Err(Error::unsupported(BinOp::Mul, &lhs, &rhs)),
+        })
+    }
+    #[allow(clippy::cast_precision_loss)]
+    fn div(lhs: Self, rhs: Self, mode: ArithmeticMode) -> Result<Self, Error> {
+        Ok(match (lhs, rhs) {
+            (Self::Int(_), Self::Int(0)) => return Err(Error::DivisionByZero),
+            // Only `i64::MIN / -1` overflows, giving `2^63`.
+            (Self::Int(lhs), Self::Int(rhs)) => {
+                mode.resolve(lhs.checked_div(rhs), || -&BigInt::from(lhs))?
+            }
+            (Self::Int(lhs), Self::Float(rhs)) => Self::Float(lhs as f64 / rhs),
+            (Self::Float(lhs), Self::Int(rhs)) => Self::Float(lhs / rhs as f64),
+            (Self::Float(lhs), Self::Float(rhs)) => Self::Float(lhs / rhs),
+            (lhs, rhs) => return Err(Error::unsupported(BinOp::Div, &lhs, &rhs)),
+        })
+    }
+    #[allow(clippy::cast_precision_loss)]
+    /// The remainder always fits, so unlike the other operators this ignores the mode.
+    fn rem(lhs: Self, rhs: Self) -> Result<Self, Error> {
+        Ok(match (lhs, rhs) {
+            (Self::Int(_), Self::Int(0)) => return Err(Error::DivisionByZero),
+            // `i64::MIN % -1` overflows in `i64` arithmetic, but is exactly 0.
+            (Self::Int(_), Self::Int(-1)) => Self::Int(0),
+            (Self::Int(lhs), Self::Int(rhs)) => Self::Int(lhs % rhs),
+            (Self::Int(lhs), Self::Float(rhs)) => Self::Float(lhs as f64 % rhs),
+            (Self::Float(lhs), Self::Int(rhs)) => Self::Float(lhs % rhs as f64),
+            (Self::Float(lhs), Self::Float(rhs)) => Self::Float(lhs % rhs),
+            (lhs, rhs) => return Err(Error::unsupported(BinOp::Mod, &lhs, &rhs)),
+        })
+    }
+    #[allow(clippy::cast_precision_loss)]
+    fn pow(lhs: Self, rhs: Self, mode: ArithmeticMode) -> Result<Self, Error> {
+        Ok(match (lhs, rhs) {
+            (Self::Int(base), Self::Int(exp)) if exp < 0 => {
+                Self::Float((base as f64).powf(exp as f64))
+            }
+            // The exact power can be too large to compute, so the modes don't go through it.
+            (Self::Int(base), Self::Int(exp)) => match Self::int_pow(base, exp) {
+                Some(int) => Self::Int(int),
+                None => match mode {
+                    ArithmeticMode::Checked => return Err(Error::IntegerOverflow),
+                    ArithmeticMode::Wrapping => {
+                        Self::Int(Self::wrapping_pow(base, exp.unsigned_abs()))
+                    }
+                    ArithmeticMode::Saturating if base < 0 && exp % 2 == 1 => Self::Int(i64::MIN),
+                    ArithmeticMode::Saturating => Self::Int(i64::MAX),
+                    ArithmeticMode::Promote => Self::Float((base as f64).powf(exp as f64)),
+                    ArithmeticMode::Unbounded => Self::big_pow(&base.into(), &exp.into())?,
+                },
+            },
+            (Self::Int(base), Self::Float(exp)) => Self::Float((base as f64).powf(exp)),
+            (Self::Float(base), Self::Int(exp)) => Self::Float(base.powf(exp as f64)),
+            (Self::Float(base), Self::Float(exp)) => Self::Float(base.powf(exp)),
+            (lhs, rhs) => return Err(Error::unsupported(BinOp::Pow, &lhs, &rhs)),
+        })
+    }
+    fn int_pow(base: i64, exp: i64) -> Option<i64> {
+        match u32::try_from(exp) {
+            Ok(exp) => base.checked_pow(exp),
+            // Only these bases survive an exponent that large.
+            Err(_) => match base {
+                0 | 1 => Some(base),
+                -1 => Some(if exp % 2 == 0 { 1 } else { -1 }),
+                _ => None,
+            },
+        }
+    }
+    /// Square and multiply, since `i64::wrapping_pow` only takes a `u32` exponent.
+    fn wrapping_pow(mut base: i64, mut exp: u64) -> i64 {
+        let mut result: i64 = 1;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.wrapping_mul(base);
+            }
+            base = base.wrapping_mul(base);
+            exp >>= 1;
         }
+        result
+    }
+    /// Arithmetic with at least one `BigInt` operand is always exact.
+    fn run_big_binop(lhs: &BigInt, rhs: &BigInt, op: BinOp) -> Result<Self, Error> {
+        Ok(match op {
+            BinOp::Add => Self::big(lhs + rhs),
+            BinOp::Sub => Self::big(lhs - rhs),
+            BinOp::Mul => Self::big(lhs * rhs),
+            BinOp::Div | BinOp::Mod => {
+                let (quotient, remainder) = lhs.div_rem(rhs).ok_or(Error::DivisionByZero)?;
+                Self::big(if op == BinOp::Div {
+                    quotient
+                } else {
+                    remainder
+                })
+            }
+            BinOp::Pow => Self::big_pow(lhs, rhs)?,
+            BinOp::LE | BinOp::LT | BinOp::GE | BinOp::GT | BinOp::Eq | BinOp::Ne => {
+                Self::Int(i64::from(Self::holds(op, Some(lhs.cmp(rhs)))))
+            }
+            BinOp::BitAnd => Self::big(lhs & rhs),
+            BinOp::BitOr => Self::big(lhs | rhs),
+            BinOp::BitXor => Self::big(lhs ^ rhs),
+            BinOp::Shl | BinOp::Shr => {
+                let count = rhs.to_i64().unwrap_or(if rhs.is_negative() {
+                    i64::MIN
+                } else {
+                    i64::MAX
+                });
+                let count = Self::shift_count(count)?;
+                Self::big(if op == BinOp::Shl {
+                    lhs << count
+                } else {
+                    lhs >> count
+                })
+            }
+        })
+    }
+    /// Fails rather than allocating a result of more than `MAX_BIG_BITS`.
+    fn big_pow(base: &BigInt, exp: &BigInt) -> Result<Self, Error> {
+        const MAX_BIG_BITS: u64 = 1 << 20;
+        if exp.is_negative() {
+            return Ok(Self::Float(base.to_f64().powf(exp.to_f64())));
+        }
+        // 0, 1 and -1 only depend on whether the exponent is zero or odd.
+        if base.bits() <= 1 {
+            let exp = if exp.is_zero() {
+                0
+            } else {
+                2 - (exp.wrapping_to_i64() & 1).unsigned_abs()
+            };
+            return Ok(Self::big(base.pow(exp)));
+        }
+        let exp = exp
+            .to_i64()
+            .map(i64::unsigned_abs)
+            .filter(|exp| exp.saturating_mul(base.bits()) <= MAX_BIG_BITS)
+            .ok_or(Error::IntegerOverflow)?;
+        Ok(Self::big(base.pow(exp)))
+    }
+    /// A left shift that loses bits overflows, and is handled according to `mode`.
+    fn bitwise(lhs: &Self, rhs: &Self, op: BinOp, mode: ArithmeticMode) -> Result<Self, Error> {
+        let (&Self::Int(lhs), &Self::Int(rhs)) = (lhs, rhs) else {
+            return Err(Error::unsupported(op, lhs, rhs));
+        };
+        Ok(Self::Int(match op {
+            BinOp::BitAnd => lhs & rhs,
+            BinOp::BitOr => lhs | rhs,
+            BinOp::BitXor => lhs ^ rhs,
+            BinOp::Shl => {
+                let count = Self::shift_count(rhs)?;
+                let shifted = lhs << count;
+                let exact = (shifted >> count == lhs).then_some(shifted);
+                return mode.resolve(exact, || &BigInt::from(lhs) << count);
+            }
+            BinOp::Shr => lhs >> Self::shift_count(rhs)?,
+            _ => unreachable!("{op:?} is not bitwise"),
+        }))
+    }
+    /// Shifting by 64 or more isn't defined for `i64`, so it's an error rather than zero.
+    fn shift_count(count: i64) -> Result<u32, Error> {
+        u32::try_from(count)
+            .ok()
+            .filter(|count| *count < i64::BITS)
+            .ok_or(Error::InvalidShift(count))
+    }
+    /// Numbers compare by value across `Int` and `Float`, strings compare lexicographically.
+    /// Lists are equal when their items are, but cannot be ordered.
+    /// Values of unrelated kinds are never equal and cannot be ordered.
+    #[allow(clippy::cast_precision_loss)]
+    fn compare(lhs: &Self, rhs: &Self, op: BinOp) -> Result<Self, Error> {
+        let ordering = match (lhs, rhs) {
+            (Self::Int(lhs), Self::Int(rhs)) => Some(lhs.cmp(rhs)),
+            (Self::Int(_) | Self::BigInt(_), Self::Int(_) | Self::BigInt(_)) => {
+                Some(lhs.to_big().cmp(&rhs.to_big()))
+            }
+            (Self::BigInt(lhs), Self::Float(rhs)) => lhs.to_f64().partial_cmp(rhs),
+            (Self::Float(lhs), Self::BigInt(rhs)) => lhs.partial_cmp(&rhs.to_f64()),
+            (Self::Int(lhs), Self::Float(rhs)) => (*lhs as f64).partial_cmp(rhs),
+            (Self::Float(lhs), Self::Int(rhs)) => lhs.partial_cmp(&(*rhs as f64)),
+            (Self::Float(lhs), Self::Float(rhs)) => lhs.partial_cmp(rhs),
+            (Self::Str(lhs), Self::Str(rhs)) if Rc::ptr_eq(lhs, rhs) => Some(Ordering::Equal),
+            (Self::Str(lhs), Self::Str(rhs)) => Some(lhs.cmp(rhs)),
+            (Self::List(lhs), Self::List(rhs)) if matches!(op, BinOp::Eq | BinOp::Ne) => {
+                Self::same_items(lhs, rhs)?.then_some(Ordering::Equal)
+            }
+            (Self::Map(lhs), Self::Map(rhs)) if matches!(op, BinOp::Eq | BinOp::Ne) => {
+                Self::same_entries(lhs, rhs)?.then_some(Ordering::Equal)
+            }
+            _ if matches!(op, BinOp::Eq | BinOp::Ne) => None,
+            _ => return Err(Error::unsupported(op, lhs, rhs)),
+        };
+        Ok(Self::Int(i64::from(Self::holds(op, ordering))))
+    }
+    /// Whether the comparison `op` holds for operands ordered by `ordering`.
+    fn holds(op: BinOp, ordering: Option<Ordering>) -> bool {
+        match op {
+            BinOp::LT => ordering == Some(Ordering::Less),
+            BinOp::LE => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            BinOp::GT => ordering == Some(Ordering::Greater),
+            BinOp::GE => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            BinOp::Eq => ordering == Some(Ordering::Equal),
+            BinOp::Ne => ordering != Some(Ordering::Equal),
+            _ => unreachable!("{op:?} is not a comparison"),
+        }
+    }
+    fn same_items(lhs: &RefCell<Vec<Value>>, rhs: &RefCell<Vec<Value>>) -> Result<bool, Error> {
+        if std::ptr::eq(lhs, rhs) {
+            return Ok(true);
+        }
+        let (lhs, rhs) = (lhs.borrow(), rhs.borrow());
+        if lhs.len() != rhs.len() {
+            return Ok(false);
+        }
+        for (lhs, rhs) in lhs.iter().zip(rhs.iter()) {
+            if Self::compare(lhs, rhs, BinOp::Eq)? != Self::Int(1) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+    fn same_entries(lhs: &RefCell<Map>, rhs: &RefCell<Map>) -> Result<bool, Error> {
+        if std::ptr::eq(lhs, rhs) {
+            return Ok(true);
+        }
+        let (lhs, rhs) = (lhs.borrow(), rhs.borrow());
+        if lhs.len() != rhs.len() {
+            return Ok(false);
+        }
+        for (key, lhs) in lhs.iter() {
+            let Some(rhs) = rhs.get(key) else {
+                return Ok(false);
+            };
+            if Self::compare(lhs, rhs, BinOp::Eq)? != Self::Int(1) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// How a machine stores values on its stack, so that it can run either on
+/// `Value` itself or on a compact representation like `packed::Packed`.
+pub trait Slot: Clone + fmt::Debug {
+    fn from_value(value: Value) -> Self;
+    fn into_value(self) -> Value;
+    fn run_binop(lhs: Self, rhs: Self, op: BinOp, mode: ArithmeticMode) -> Result<Self, Error>;
+    fn is_truthy(&self) -> bool;
+
+    /// Pushed by `LoadConst`, lists and maps must not be shared with the constant.
+    fn load_const(constant: &Self) -> Self {
+        Self::from_value(constant.clone().into_value().deep_copy())
+    }
+    fn run_unaryop(operand: Self, op: UnaryOp, mode: ArithmeticMode) -> Result<Self, Error> {
+        Value::run_unaryop(&operand.into_value(), op, mode).map(Self::from_value)
+    }
+
+    fn build_list(items: Vec<Self>) -> Self {
+        Self::from_value(Value::list(
+            items.into_iter().map(Slot::into_value).collect(),
+        ))
+    }
+    fn index(list: Self, index: Self) -> Result<Self, Error> {
+        Value::index(&list.into_value(), &index.into_value()).map(Self::from_value)
+    }
+    fn store_index(list: Self, index: Self, value: Self) -> Result<(), Error> {
+        Value::store_index(&list.into_value(), &index.into_value(), value.into_value())
+    }
+    fn append(list: &Self, value: Self) -> Result<(), Error> {
+        Value::append(&list.clone().into_value(), value.into_value())
+    }
+    fn length(list: Self) -> Result<Self, Error> {
+        Value::length(&list.into_value()).map(Self::from_value)
+    }
+    fn build_map(items: Vec<Self>) -> Result<Self, Error> {
+        Value::build_map(items.into_iter().map(Slot::into_value).collect()).map(Self::from_value)
+    }
+    fn get_item(map: Self, key: Self) -> Result<Self, Error> {
+        Value::get_item(&map.into_value(), &key.into_value()).map(Self::from_value)
+    }
+    fn set_item(map: Self, key: Self, value: Self) -> Result<(), Error> {
+        Value::set_item(&map.into_value(), &key.into_value(), value.into_value())
+    }
+    fn has_key(map: Self, key: Self) -> Result<Self, Error> {
+        Value::has_key(&map.into_value(), &key.into_value()).map(Self::from_value)
+    }
+    fn keys(map: Self) -> Result<Self, Error> {
+        Value::keys(&map.into_value()).map(Self::from_value)
+    }
+    fn to_str(value: Self) -> Self {
+        Self::from_value(value.into_value().to_str())
+    }
+    fn to_int(value: Self) -> Result<Self, Error> {
+        value.into_value().to_int().map(Self::from_value)
+    }
+    fn to_float(value: Self) -> Result<Self, Error> {
+        value.into_value().to_float().map(Self::from_value)
     }
 }
 
-impl<'a> From<&Value<'a>> for bool {
-    fn from(value: &Value<'a>) -> Self {
-        match value {
-            Value::Int(int) => *int != 0,
-            Value::Str(str) => str.is_empty(),
-            Value::Float(float) => *float != 0.0,
+impl Slot for Value {
+    #[inline]
+    fn from_value(value: Value) -> Self {
+        value
+    }
+    #[inline]
+    fn into_value(self) -> Value {
+        self
+    }
+    #[inline]
+    fn run_binop(lhs: Self, rhs: Self, op: BinOp, mode: ArithmeticMode) -> Result<Self, Error> {
+        Value::run_binop(lhs, rhs, op, mode)
+    }
+    #[inline]
+    fn is_truthy(&self) -> bool {
+        Value::is_truthy(self)
+    }
+    #[inline]
+    fn load_const(constant: &Self) -> Self {
+        constant.deep_copy()
+    }
+}
+
+impl From<&Value> for bool {
+    fn from(value: &Value) -> Self {
+        value.is_truthy()
+    }
+}
+
+/// Formats values the way `Print` shows them: strings without quotes, floats
+/// always with a fraction or exponent, and strings inside lists and maps quoted.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(int) => write!(f, "{int}"),
+            Self::BigInt(big) => write!(f, "{big}"),
+            Self::Float(float) if float.is_nan() => write!(f, "nan"),
+            Self::Float(float) => write!(f, "{float:?}"),
+            Self::Str(str) => write!(f, "{str}"),
+            Self::List(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt_nested(f)?;
+                }
+                write!(f, "]")
+            }
+            Self::Map(entries) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in entries.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: ")?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
 
-impl<'a> From<i64> for Value<'a> {
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Self {
+        Self::list(items)
+    }
+}
+
+impl From<Map> for Value {
+    fn from(entries: Map) -> Self {
+        Self::map(entries)
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(big: BigInt) -> Self {
+        Self::big(big)
+    }
+}
+
+impl From<i64> for Value {
     fn from(value: i64) -> Self {
         Self::Int(value)
     }
 }
 
-impl<'a> From<f64> for Value<'a> {
+impl From<f64> for Value {
     fn from(value: f64) -> Self {
         Self::Float(value)
     }
 }
 
-impl<'a> From<&'a str> for Value<'a> {
-    fn from(value: &'a str) -> Self {
-        Self::Str(Cow::Borrowed(value))
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
     }
 }
 
-impl<'a> From<String> for Value<'a> {
+impl From<String> for Value {
     fn from(value: String) -> Self {
-        Self::Str(Cow::Owned(value))
+        Self::Str(value.into())
     }
 }
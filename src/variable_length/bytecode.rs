@@ -1,6 +1,11 @@
 use std::{fmt, ops::Deref};
 
-use crate::{BinOp, Value};
+use super::vm::Vm;
+use crate::{
+    constants::Constants,
+    instr::{self, Instr, Target},
+    Assembler, BinOp, Error, UnaryOp, Value,
+};
 
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
@@ -13,73 +18,317 @@ pub enum OpCode {
 
     LoadConst,
 
-    Jump,
-    PopJumpIfFalse,
+    Jump8,
+    Jump16,
+    Jump32,
+    PopJumpIfFalse8,
+    PopJumpIfFalse16,
+    PopJumpIfFalse32,
+
+    BuildList,
+    Index,
+    StoreIndex,
+    Len,
+    Append,
+    BuildMap,
+    GetItem,
+    SetItem,
+    HasKey,
+    Keys,
+    UnaryOp,
+    PopJumpIfTrue8,
+    PopJumpIfTrue16,
+    PopJumpIfTrue32,
+    JumpIfFalseOrPop8,
+    JumpIfFalseOrPop16,
+    JumpIfFalseOrPop32,
+    JumpIfTrueOrPop8,
+    JumpIfTrueOrPop16,
+    JumpIfTrueOrPop32,
+    Pop,
+    Swap,
+    Rot3,
+    Over,
+    PopN,
+    Print,
+    ToStr,
+    ToInt,
+    ToFloat,
+    Format,
 
     LEN,
 }
 
 impl OpCode {
-    pub const JUMP_SIZE: usize = usize::BITS as usize / 8;
+    /// The operand size of jumps emitted while building, wide enough to be patched in place.
+    pub const JUMP_SIZE: usize = 4;
     #[must_use]
     pub fn size(self) -> Option<u8> {
         Some(match self {
-            Self::LoadConst => 4,
-            Self::NOP | Self::LEN => 0,
-            Self::BinOp | Self::Dup => 1,
-            #[allow(clippy::cast_possible_truncation)]
-            Self::Jump | Self::PopJumpIfFalse => Self::JUMP_SIZE as u8,
+            Self::LoadConst
+            | Self::BuildList
+            | Self::BuildMap
+            | Self::PopN
+            | Self::Format
+            | Self::Jump32
+            | Self::PopJumpIfFalse32
+            | Self::PopJumpIfTrue32
+            | Self::JumpIfFalseOrPop32
+            | Self::JumpIfTrueOrPop32 => 4,
+            Self::Jump16
+            | Self::PopJumpIfFalse16
+            | Self::PopJumpIfTrue16
+            | Self::JumpIfFalseOrPop16
+            | Self::JumpIfTrueOrPop16 => 2,
+            Self::BinOp
+            | Self::UnaryOp
+            | Self::Jump8
+            | Self::PopJumpIfFalse8
+            | Self::PopJumpIfTrue8
+            | Self::JumpIfFalseOrPop8
+            | Self::JumpIfTrueOrPop8 => 1,
+            Self::NOP
+            | Self::Dup
+            | Self::Pop
+            | Self::Swap
+            | Self::Rot3
+            | Self::Over
+            | Self::Print
+            | Self::ToStr
+            | Self::ToInt
+            | Self::ToFloat
+            | Self::Index
+            | Self::StoreIndex
+            | Self::Len
+            | Self::Append
+            | Self::GetItem
+            | Self::SetItem
+            | Self::HasKey
+            | Self::Keys
+            | Self::LEN => 0,
         })
     }
+    /// The opcode encoding `instr`, using `width` byte operands for jumps.
+    #[must_use]
+    pub fn of(instr: &Instr, width: u8) -> Self {
+        match (instr, width) {
+            (Instr::Nop, _) => Self::NOP,
+            (Instr::Dup, _) => Self::Dup,
+            (Instr::Pop, _) => Self::Pop,
+            (Instr::Swap, _) => Self::Swap,
+            (Instr::Rot3, _) => Self::Rot3,
+            (Instr::Over, _) => Self::Over,
+            (Instr::PopN(_), _) => Self::PopN,
+            (Instr::Print, _) => Self::Print,
+            (Instr::ToStr, _) => Self::ToStr,
+            (Instr::ToInt, _) => Self::ToInt,
+            (Instr::ToFloat, _) => Self::ToFloat,
+            (Instr::Format(_), _) => Self::Format,
+            (Instr::BinOp(_), _) => Self::BinOp,
+            (Instr::UnaryOp(_), _) => Self::UnaryOp,
+            (Instr::LoadConst(_), _) => Self::LoadConst,
+            (Instr::BuildList(_), _) => Self::BuildList,
+            (Instr::Index, _) => Self::Index,
+            (Instr::StoreIndex, _) => Self::StoreIndex,
+            (Instr::Len, _) => Self::Len,
+            (Instr::Append, _) => Self::Append,
+            (Instr::BuildMap(_), _) => Self::BuildMap,
+            (Instr::GetItem, _) => Self::GetItem,
+            (Instr::SetItem, _) => Self::SetItem,
+            (Instr::HasKey, _) => Self::HasKey,
+            (Instr::Keys, _) => Self::Keys,
+            (Instr::Jump(_), 1) => Self::Jump8,
+            (Instr::Jump(_), 2) => Self::Jump16,
+            (Instr::Jump(_), _) => Self::Jump32,
+            (Instr::PopJumpIfFalse(_), 1) => Self::PopJumpIfFalse8,
+            (Instr::PopJumpIfFalse(_), 2) => Self::PopJumpIfFalse16,
+            (Instr::PopJumpIfFalse(_), _) => Self::PopJumpIfFalse32,
+            (Instr::PopJumpIfTrue(_), 1) => Self::PopJumpIfTrue8,
+            (Instr::PopJumpIfTrue(_), 2) => Self::PopJumpIfTrue16,
+            (Instr::PopJumpIfTrue(_), _) => Self::PopJumpIfTrue32,
+            (Instr::JumpIfFalseOrPop(_), 1) => Self::JumpIfFalseOrPop8,
+            (Instr::JumpIfFalseOrPop(_), 2) => Self::JumpIfFalseOrPop16,
+            (Instr::JumpIfFalseOrPop(_), _) => Self::JumpIfFalseOrPop32,
+            (Instr::JumpIfTrueOrPop(_), 1) => Self::JumpIfTrueOrPop8,
+            (Instr::JumpIfTrueOrPop(_), 2) => Self::JumpIfTrueOrPop16,
+            (Instr::JumpIfTrueOrPop(_), _) => Self::JumpIfTrueOrPop32,
+        }
+    }
+    /// The narrowest jump operand able to hold the relative `offset`.
+    #[must_use]
+    pub fn jump_width(offset: isize) -> u8 {
+        if i8::try_from(offset).is_ok() {
+            1
+        } else if i16::try_from(offset).is_ok() {
+            2
+        } else {
+            4
+        }
+    }
 }
 
 #[derive(Debug, Default)]
-pub struct Pool<'a> {
+pub struct Pool {
     items: Vec<u8>,
-    pub constants: Vec<Value<'a>>,
+    pub constants: Constants,
 }
 
-impl<'a> Pool<'a> {
+impl Pool {
     #[inline]
     pub fn push_dup(&mut self) {
         self.items.push(OpCode::Dup as u8);
     }
     #[inline]
+    pub fn push_pop(&mut self) {
+        self.items.push(OpCode::Pop as u8);
+    }
+    #[inline]
+    pub fn push_swap(&mut self) {
+        self.items.push(OpCode::Swap as u8);
+    }
+    #[inline]
+    pub fn push_rot3(&mut self) {
+        self.items.push(OpCode::Rot3 as u8);
+    }
+    #[inline]
+    pub fn push_over(&mut self) {
+        self.items.push(OpCode::Over as u8);
+    }
+    #[inline]
+    pub fn push_pop_n(&mut self, len: u32) {
+        self.items.push(OpCode::PopN as u8);
+        self.items.extend_from_slice(&len.to_le_bytes());
+    }
+    #[inline]
+    pub fn push_print(&mut self) {
+        self.items.push(OpCode::Print as u8);
+    }
+    #[inline]
+    pub fn push_to_str(&mut self) {
+        self.items.push(OpCode::ToStr as u8);
+    }
+    #[inline]
+    pub fn push_to_int(&mut self) {
+        self.items.push(OpCode::ToInt as u8);
+    }
+    #[inline]
+    pub fn push_to_float(&mut self) {
+        self.items.push(OpCode::ToFloat as u8);
+    }
+    #[inline]
+    pub fn push_format(&mut self, template: &str) {
+        let index = u32::try_from(self.insert_const(Value::Str(template.into()))).unwrap();
+        self.items.push(OpCode::Format as u8);
+        self.items.extend_from_slice(&index.to_le_bytes());
+    }
+    #[inline]
+    pub fn push_build_list(&mut self, len: u32) {
+        self.items.push(OpCode::BuildList as u8);
+        self.items.extend_from_slice(&len.to_le_bytes());
+    }
+    /// Pushes a `BuildMap` of the `len` key and value pairs on top of the stack.
+    #[inline]
+    pub fn push_build_map(&mut self, len: u32) {
+        self.items.push(OpCode::BuildMap as u8);
+        self.items.extend_from_slice(&len.to_le_bytes());
+    }
+    #[inline]
+    pub fn push_get_item(&mut self) {
+        self.items.push(OpCode::GetItem as u8);
+    }
+    #[inline]
+    pub fn push_set_item(&mut self) {
+        self.items.push(OpCode::SetItem as u8);
+    }
+    #[inline]
+    pub fn push_has_key(&mut self) {
+        self.items.push(OpCode::HasKey as u8);
+    }
+    #[inline]
+    pub fn push_keys(&mut self) {
+        self.items.push(OpCode::Keys as u8);
+    }
+    #[inline]
     pub fn push_jump(&mut self, pos: usize) -> usize {
-        self.items.push(OpCode::Jump as u8);
-        self.items.extend_from_slice(&pos.to_le_bytes());
-        self.items.len() - OpCode::JUMP_SIZE
+        self.push_jump_op(OpCode::Jump32, pos)
     }
     #[inline]
     pub fn push_pop_jump_if_false(&mut self, pos: usize) -> usize {
-        self.items.push(OpCode::PopJumpIfFalse as u8);
-        self.items.extend_from_slice(&pos.to_le_bytes());
+        self.push_jump_op(OpCode::PopJumpIfFalse32, pos)
+    }
+    #[inline]
+    pub fn push_pop_jump_if_true(&mut self, pos: usize) -> usize {
+        self.push_jump_op(OpCode::PopJumpIfTrue32, pos)
+    }
+    #[inline]
+    pub fn push_jump_if_false_or_pop(&mut self, pos: usize) -> usize {
+        self.push_jump_op(OpCode::JumpIfFalseOrPop32, pos)
+    }
+    #[inline]
+    pub fn push_jump_if_true_or_pop(&mut self, pos: usize) -> usize {
+        self.push_jump_op(OpCode::JumpIfTrueOrPop32, pos)
+    }
+    fn push_jump_op(&mut self, op_code: OpCode, pos: usize) -> usize {
+        self.items.push(op_code as u8);
+        let end = self.items.len() + OpCode::JUMP_SIZE;
+        let offset = i32::try_from(instr::relative(end, pos)).unwrap();
+        self.items.extend_from_slice(&offset.to_le_bytes());
         self.items.len() - OpCode::JUMP_SIZE
     }
     #[inline]
     pub fn patch_jump(&mut self, pos: usize) {
-        let here = self.len();
-        let slice = &mut self.items[pos..pos + OpCode::JUMP_SIZE];
-        debug_assert_eq!(slice, &[0; OpCode::JUMP_SIZE]);
-        slice.copy_from_slice(&here.to_le_bytes());
+        let end = pos + OpCode::JUMP_SIZE;
+        let offset = i32::try_from(instr::relative(end, self.len())).unwrap();
+        self.items[pos..end].copy_from_slice(&offset.to_le_bytes());
+    }
+    /// Appends `other`, merging its constants into this pool's table.
+    ///
+    /// Jumps are relative, so only `LoadConst` and `Format` operands need rewriting. A jump
+    /// to the end of `other` lands on whatever gets pushed after it.
+    pub fn append(&mut self, other: Pool) {
+        let indices: Vec<u32> = other
+            .constants
+            .into_vec()
+            .into_iter()
+            .map(|value| u32::try_from(self.insert_const(value)).unwrap())
+            .collect();
+        let mut head = self.items.len();
+        self.items.extend_from_slice(&other.items);
+        while head < self.items.len() {
+            let op_byte = self.items[head];
+            assert!(op_byte < OpCode::LEN as u8, "invalid opcode {op_byte}");
+            let op: OpCode = unsafe { std::mem::transmute(op_byte) };
+            head += 1;
+            if let OpCode::LoadConst | OpCode::Format = op {
+                let index = u32::from_le_bytes(read(&self.items, head));
+                let remapped = indices[index as usize].to_le_bytes();
+                self.items[head..head + remapped.len()].copy_from_slice(&remapped);
+            }
+            head += op.size().unwrap() as usize;
+        }
+    }
+    /// Re-encodes every jump with the narrowest operand that fits its target.
+    ///
+    /// Handles returned by the jump helpers are invalid afterwards, so this should be done last.
+    pub fn relax(&mut self) {
+        let instrs = self.decode().unwrap();
+        *self = Self::from_instrs(&instrs, std::mem::take(&mut self.constants).into_vec());
     }
     #[inline]
-    pub fn push_const(&mut self, value: Value<'a>) -> usize {
+    pub fn push_const(&mut self, value: Value) -> usize {
         self.items.push(OpCode::LoadConst as u8);
 
-        let index = if let Some(index) = self.constants.iter().position(|val| val == &value) {
-            index
-        } else {
-            self.constants.push(value);
-            self.constants.len() - 1
-        };
+        let index = self.insert_const(value);
         let index_u32 = u32::try_from(index).unwrap();
         self.items.extend_from_slice(&index_u32.to_le_bytes());
 
         index
     }
+    pub fn insert_const(&mut self, value: Value) -> usize {
+        self.constants.insert(value)
+    }
     #[inline]
-    pub fn push_literal<V: Into<Value<'a>>>(&mut self, value: V) -> usize {
+    pub fn push_literal<V: Into<Value>>(&mut self, value: V) -> usize {
         self.push_const(value.into())
     }
     #[inline]
@@ -88,102 +337,291 @@ impl<'a> Pool<'a> {
         self.items.push(binop as u8);
     }
     #[inline]
-    pub fn push_if<F>(&mut self, body: F)
-    where
-        F: FnOnce(&mut Self),
-    {
-        let jump = self.push_pop_jump_if_false(0);
-        body(self);
-        self.patch_jump(jump);
-    }
-    #[inline]
-    pub fn push_if_or_else<F1, F2>(&mut self, body: F1, or_else: F2)
-    where
-        F1: FnOnce(&mut Self),
-        F2: FnOnce(&mut Self),
-    {
-        let jump_if = self.push_pop_jump_if_false(0);
-        body(self);
-        let jump_else = self.push_jump(0);
-        self.patch_jump(jump_if);
-        or_else(self);
-        self.patch_jump(jump_else);
-    }
-    #[inline]
-    pub fn push_loop<F>(&mut self, body: F)
-    where
-        F: FnOnce(&mut Self),
-    {
-        let start = self.len();
-        body(self);
-        self.push_jump(start);
-    }
-    #[inline]
-    pub fn push_while_loop<F1, F2>(&mut self, condition: F1, body: F2)
-    where
-        F1: FnOnce(&mut Self),
-        F2: FnOnce(&mut Self),
-    {
-        let start = self.len();
-        condition(self);
-        let jump = self.push_pop_jump_if_false(0);
-        body(self);
-        self.push_jump(start);
-        self.patch_jump(jump);
+    pub fn push_unaryop(&mut self, op: UnaryOp) {
+        self.items.push(OpCode::UnaryOp as u8);
+        self.items.push(op as u8);
     }
     #[must_use]
     pub fn as_bytes(&self) -> &[u8] {
         self
     }
-}
-
-impl<'a> Deref for Pool<'a> {
-    type Target = [u8];
-    fn deref(&self) -> &Self::Target {
-        &self.items
+    pub fn decode(&self) -> Result<Vec<Instr>, Error> {
+        self.decode_with_offsets().map(|(instrs, _)| instrs)
     }
-}
-
-impl<'a> fmt::Display for Pool<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn decode_with_offsets(&self) -> Result<(Vec<Instr>, Vec<usize>), Error> {
+        let mut instrs = vec![];
+        let mut offsets = vec![];
         let mut head = 0;
         while head < self.len() {
             let op_byte = self[head];
-            assert!(op_byte < OpCode::LEN as u8);
+            if op_byte >= OpCode::LEN as u8 {
+                return Err(Error::InvalidOpCode(op_byte));
+            }
             let op: OpCode = unsafe { std::mem::transmute(op_byte) };
-
-            write!(f, "{head} ")?;
+            offsets.push(head);
             head += 1;
 
-            match op {
-                OpCode::LEN => unreachable!(),
-                OpCode::Dup => writeln!(f, "Dup")?,
-                OpCode::NOP => writeln!(f, "Nop")?,
-                OpCode::BinOp => {
-                    let binop_byte = self[head];
-                    let binop: BinOp = unsafe { std::mem::transmute(binop_byte) };
-                    writeln!(f, "BinOp ({binop:?})")?;
-                    head += 1;
+            let size = op.size().unwrap() as usize;
+            if head + size > self.len() {
+                return Err(Error::UnexpectedEnd);
+            }
+            let target = || instr::absolute(head + size, read_offset(self, head, op)).map(Target);
+            instrs.push(match op {
+                OpCode::NOP => Instr::Nop,
+                OpCode::Dup => Instr::Dup,
+                OpCode::Pop => Instr::Pop,
+                OpCode::Swap => Instr::Swap,
+                OpCode::Rot3 => Instr::Rot3,
+                OpCode::Over => Instr::Over,
+                OpCode::PopN => Instr::PopN(u32::from_le_bytes(read(self, head))),
+                OpCode::Print => Instr::Print,
+                OpCode::ToStr => Instr::ToStr,
+                OpCode::ToInt => Instr::ToInt,
+                OpCode::ToFloat => Instr::ToFloat,
+                OpCode::Format => Instr::Format(u32::from_le_bytes(read(self, head))),
+                OpCode::BinOp => Instr::BinOp(BinOp::try_from(self[head])?),
+                OpCode::UnaryOp => Instr::UnaryOp(UnaryOp::try_from(self[head])?),
+                OpCode::LoadConst => Instr::LoadConst(u32::from_le_bytes(read(self, head))),
+                OpCode::BuildList => Instr::BuildList(u32::from_le_bytes(read(self, head))),
+                OpCode::Index => Instr::Index,
+                OpCode::StoreIndex => Instr::StoreIndex,
+                OpCode::Len => Instr::Len,
+                OpCode::Append => Instr::Append,
+                OpCode::BuildMap => Instr::BuildMap(u32::from_le_bytes(read(self, head))),
+                OpCode::GetItem => Instr::GetItem,
+                OpCode::SetItem => Instr::SetItem,
+                OpCode::HasKey => Instr::HasKey,
+                OpCode::Keys => Instr::Keys,
+                OpCode::Jump8 | OpCode::Jump16 | OpCode::Jump32 => Instr::Jump(target()?),
+                OpCode::PopJumpIfFalse8 | OpCode::PopJumpIfFalse16 | OpCode::PopJumpIfFalse32 => {
+                    Instr::PopJumpIfFalse(target()?)
                 }
-                OpCode::LoadConst => {
-                    let index = u32::from_le_bytes(read(self, head)) as usize;
-                    let value = &self.constants[index];
-                    writeln!(f, "LoadConst ({index}) ({value:?})")?;
-                    head += 4;
+                OpCode::PopJumpIfTrue8 | OpCode::PopJumpIfTrue16 | OpCode::PopJumpIfTrue32 => {
+                    Instr::PopJumpIfTrue(target()?)
                 }
-                OpCode::Jump => {
-                    let jump = usize::from_le_bytes(read(self, head));
-                    writeln!(f, "Jump ({jump})")?;
-                    head += OpCode::JUMP_SIZE;
+                OpCode::JumpIfFalseOrPop8
+                | OpCode::JumpIfFalseOrPop16
+                | OpCode::JumpIfFalseOrPop32 => Instr::JumpIfFalseOrPop(target()?),
+                OpCode::JumpIfTrueOrPop8
+                | OpCode::JumpIfTrueOrPop16
+                | OpCode::JumpIfTrueOrPop32 => Instr::JumpIfTrueOrPop(target()?),
+                OpCode::LEN => unreachable!(),
+            });
+            head += size;
+        }
+        offsets.push(head);
+        instr::resolve_targets(&mut instrs, &offsets)?;
+        Ok((instrs, offsets))
+    }
+    /// Encodes `instrs` with every jump as narrow as its target allows.
+    #[must_use]
+    pub fn from_instrs(instrs: &[Instr], constants: Vec<Value>) -> Self {
+        let widths = Self::jump_widths(instrs);
+        let offsets = Self::layout(instrs, &widths);
+        let mut pool = Self {
+            items: Vec::with_capacity(*offsets.last().unwrap()),
+            constants: constants.into(),
+        };
+        for (index, (instr, &width)) in instrs.iter().zip(&widths).enumerate() {
+            pool.items.push(OpCode::of(instr, width) as u8);
+            match *instr {
+                Instr::Nop
+                | Instr::Dup
+                | Instr::Pop
+                | Instr::Swap
+                | Instr::Rot3
+                | Instr::Over
+                | Instr::Print
+                | Instr::ToStr
+                | Instr::ToInt
+                | Instr::ToFloat
+                | Instr::Index
+                | Instr::StoreIndex
+                | Instr::Len
+                | Instr::Append
+                | Instr::GetItem
+                | Instr::SetItem
+                | Instr::HasKey
+                | Instr::Keys => (),
+                Instr::BinOp(binop) => pool.items.push(binop as u8),
+                Instr::UnaryOp(op) => pool.items.push(op as u8),
+                Instr::LoadConst(operand)
+                | Instr::PopN(operand)
+                | Instr::Format(operand)
+                | Instr::BuildList(operand)
+                | Instr::BuildMap(operand) => {
+                    pool.items.extend_from_slice(&operand.to_le_bytes());
                 }
-                OpCode::PopJumpIfFalse => {
-                    let jump = usize::from_le_bytes(read(self, head));
-                    writeln!(f, "PopJumpIfFalse ({jump})")?;
-                    head += OpCode::JUMP_SIZE;
+                Instr::Jump(target)
+                | Instr::PopJumpIfFalse(target)
+                | Instr::PopJumpIfTrue(target)
+                | Instr::JumpIfFalseOrPop(target)
+                | Instr::JumpIfTrueOrPop(target) => {
+                    let offset = instr::relative(offsets[index + 1], offsets[target.0]);
+                    let bytes = i32::try_from(offset).unwrap().to_le_bytes();
+                    pool.items.extend_from_slice(&bytes[..width as usize]);
                 }
             }
         }
-        Ok(())
+        pool
+    }
+    /// Starts every jump at a single byte operand and widens those whose offset
+    /// doesn't fit until the layout settles. Widening only ever stretches the
+    /// distance a jump covers, so this terminates.
+    fn jump_widths(instrs: &[Instr]) -> Vec<u8> {
+        let mut widths = vec![1; instrs.len()];
+        loop {
+            let offsets = Self::layout(instrs, &widths);
+            let mut changed = false;
+            for (index, (instr, width)) in instrs.iter().zip(&mut widths).enumerate() {
+                if let Some(target) = instr.target() {
+                    let offset = instr::relative(offsets[index + 1], offsets[target.0]);
+                    let needed = OpCode::jump_width(offset);
+                    if needed > *width {
+                        *width = needed;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return widths;
+            }
+        }
+    }
+    fn layout(instrs: &[Instr], widths: &[u8]) -> Vec<usize> {
+        let mut widths = widths.iter();
+        instr::layout(instrs, |instr| {
+            1 + OpCode::of(instr, *widths.next().unwrap()).size().unwrap() as usize
+        })
+    }
+}
+
+impl Assembler for Pool {
+    type Vm = Vm;
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+    fn push_nop(&mut self) {
+        self.items.push(OpCode::NOP as u8);
+    }
+    fn push_dup(&mut self) {
+        Pool::push_dup(self);
+    }
+    fn push_pop(&mut self) {
+        Pool::push_pop(self);
+    }
+    fn push_swap(&mut self) {
+        Pool::push_swap(self);
+    }
+    fn push_rot3(&mut self) {
+        Pool::push_rot3(self);
+    }
+    fn push_over(&mut self) {
+        Pool::push_over(self);
+    }
+    fn push_pop_n(&mut self, len: usize) {
+        Pool::push_pop_n(self, u32::try_from(len).unwrap());
+    }
+    fn push_print(&mut self) {
+        Pool::push_print(self);
+    }
+    fn push_to_str(&mut self) {
+        Pool::push_to_str(self);
+    }
+    fn push_to_int(&mut self) {
+        Pool::push_to_int(self);
+    }
+    fn push_to_float(&mut self) {
+        Pool::push_to_float(self);
+    }
+    fn push_format(&mut self, template: &str) {
+        Pool::push_format(self, template);
+    }
+    fn push_build_list(&mut self, len: usize) {
+        Pool::push_build_list(self, u32::try_from(len).unwrap());
+    }
+    fn push_index(&mut self) {
+        self.items.push(OpCode::Index as u8);
+    }
+    fn push_store_index(&mut self) {
+        self.items.push(OpCode::StoreIndex as u8);
+    }
+    fn push_len(&mut self) {
+        self.items.push(OpCode::Len as u8);
+    }
+    fn push_append(&mut self) {
+        self.items.push(OpCode::Append as u8);
+    }
+    fn push_build_map(&mut self, len: usize) {
+        Pool::push_build_map(self, u32::try_from(len).unwrap());
+    }
+    fn push_get_item(&mut self) {
+        Pool::push_get_item(self);
+    }
+    fn push_set_item(&mut self) {
+        Pool::push_set_item(self);
+    }
+    fn push_has_key(&mut self) {
+        Pool::push_has_key(self);
+    }
+    fn push_keys(&mut self) {
+        Pool::push_keys(self);
+    }
+    fn push_binop(&mut self, binop: BinOp) {
+        Pool::push_binop(self, binop);
+    }
+    fn push_unaryop(&mut self, op: UnaryOp) {
+        Pool::push_unaryop(self, op);
+    }
+    fn push_const(&mut self, value: Value) -> usize {
+        Pool::push_const(self, value)
+    }
+    fn push_jump(&mut self, pos: usize) -> usize {
+        Pool::push_jump(self, pos)
+    }
+    fn push_pop_jump_if_false(&mut self, pos: usize) -> usize {
+        Pool::push_pop_jump_if_false(self, pos)
+    }
+    fn push_pop_jump_if_true(&mut self, pos: usize) -> usize {
+        Pool::push_pop_jump_if_true(self, pos)
+    }
+    fn push_jump_if_false_or_pop(&mut self, pos: usize) -> usize {
+        Pool::push_jump_if_false_or_pop(self, pos)
+    }
+    fn push_jump_if_true_or_pop(&mut self, pos: usize) -> usize {
+        Pool::push_jump_if_true_or_pop(self, pos)
+    }
+    fn patch_jump(&mut self, jump: usize) {
+        Pool::patch_jump(self, jump);
+    }
+    fn append(&mut self, other: Self) {
+        Pool::append(self, other);
+    }
+    fn constants(&self) -> &[Value] {
+        &self.constants
+    }
+    fn decode(&self) -> Result<Vec<Instr>, Error> {
+        Pool::decode(self)
+    }
+    fn from_instrs(instrs: &[Instr], constants: Vec<Value>) -> Self {
+        Pool::from_instrs(instrs, constants)
+    }
+}
+
+impl Deref for Pool {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.decode_with_offsets() {
+            Ok((instrs, offsets)) => instr::disassemble(f, &instrs, &offsets, &self.constants),
+            Err(err) => writeln!(f, "{err}"),
+        }
     }
 }
 
@@ -192,3 +630,14 @@ impl<'a> fmt::Display for Pool<'a> {
 pub fn read<const LEN: usize>(slice: &[u8], head: usize) -> [u8; LEN] {
     slice[head..head + LEN].try_into().unwrap()
 }
+
+/// Reads the signed operand of a jump encoded with `op`.
+#[inline]
+#[must_use]
+pub fn read_offset(slice: &[u8], head: usize, op: OpCode) -> isize {
+    match op.size() {
+        Some(1) => i8::from_le_bytes(read(slice, head)).into(),
+        Some(2) => i16::from_le_bytes(read(slice, head)).into(),
+        _ => isize::try_from(i32::from_le_bytes(read(slice, head))).unwrap(),
+    }
+}
@@ -1,6 +1,5 @@
 use super::{bytecode::Pool, vm};
-use crate::{BinOp, Value};
-use std::borrow::Cow;
+use crate::{bigint::BigInt, ArithmeticMode, Assembler, BinOp, Error, UnaryOp, Value};
 
 mod load_literals {
     use super::*;
@@ -14,7 +13,7 @@ mod load_literals {
             pool.push_literal(int);
         }
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         let expected_stack: Vec<Value> = ints.into_iter().map(Value::Int).collect();
         assert_eq!(stack, expected_stack);
     }
@@ -28,7 +27,7 @@ mod load_literals {
             pool.push_literal(float);
         }
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         let expected_stack: Vec<Value> = floats.into_iter().map(Value::Float).collect();
         assert_eq!(stack, expected_stack);
     }
@@ -42,11 +41,11 @@ mod load_literals {
             pool.push_literal(str);
         }
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
 
         let expected_stack: Vec<Value> = strings
             .into_iter()
-            .map(|str| Value::Str(Cow::Borrowed(str)))
+            .map(|str| Value::Str(str.into()))
             .collect();
 
         assert_eq!(stack, expected_stack);
@@ -63,7 +62,7 @@ mod binop_add {
         pool.push_literal(2);
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(stack, vec![Value::Int(3)]);
     }
 
@@ -75,7 +74,7 @@ mod binop_add {
 
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(stack, vec![Value::Float(1.23 + 4.56)]);
     }
 
@@ -90,7 +89,7 @@ mod binop_add {
         pool.push_literal(2);
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(
             stack,
             vec![Value::Float(1.0 + 0.5), Value::Float(12.5 + 2.0)]
@@ -104,8 +103,8 @@ mod binop_add {
         pool.push_literal("World!");
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
-        assert_eq!(stack, vec![Value::Str(Cow::Owned("Hello, World!".into()))]);
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Str("Hello, World!".into())]);
     }
 }
 
@@ -120,7 +119,7 @@ mod binop_sub {
         pool.push_literal(2);
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(stack, vec![Value::Int(-1)]);
     }
 
@@ -132,7 +131,7 @@ mod binop_sub {
 
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(stack, vec![Value::Float(1.23 - 4.56)]);
     }
 
@@ -147,7 +146,7 @@ mod binop_sub {
         pool.push_literal(2);
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(
             stack,
             vec![Value::Float(1.0 - 0.5), Value::Float(12.5 - 2.0)]
@@ -166,7 +165,7 @@ mod binop_mul {
         pool.push_literal(2);
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(stack, vec![Value::Int(2)]);
     }
 
@@ -178,7 +177,7 @@ mod binop_mul {
 
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(stack, vec![Value::Float(1.23 * 4.56)]);
     }
 
@@ -193,7 +192,7 @@ mod binop_mul {
         pool.push_literal(2);
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(
             stack,
             vec![Value::Float(1.0 * 0.5), Value::Float(12.5 * 2.0)]
@@ -212,17 +211,518 @@ mod binop_mul {
         pool.push_literal("hello ");
         pool.push_binop(OP);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(
             stack,
             vec![
-                Value::Str(Cow::Owned("repeat ".repeat(5))),
-                Value::Str(Cow::Owned("hello ".repeat(3)))
+                Value::Str("repeat ".repeat(5).into()),
+                Value::Str("hello ".repeat(3).into())
             ]
         );
     }
 }
 
+mod binop_div {
+    use super::*;
+    const OP: BinOp = BinOp::Div;
+
+    #[test]
+    fn int() {
+        let mut pool = Pool::default();
+        pool.push_literal(7);
+        pool.push_literal(2);
+        pool.push_binop(OP);
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Int(3)]);
+    }
+
+    #[test]
+    fn int_float() {
+        let mut pool = Pool::default();
+        pool.push_literal(7);
+        pool.push_literal(2.0);
+        pool.push_binop(OP);
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Float(3.5)]);
+    }
+
+    #[test]
+    fn by_zero() {
+        let mut pool = Pool::default();
+        pool.push_literal(7);
+        pool.push_literal(0);
+        pool.push_binop(OP);
+
+        assert_eq!(vm::create_and_run(&pool), Err(Error::DivisionByZero));
+    }
+}
+
+mod binop_mod {
+    use super::*;
+    const OP: BinOp = BinOp::Mod;
+
+    #[test]
+    fn int() {
+        let mut pool = Pool::default();
+        pool.push_literal(7);
+        pool.push_literal(3);
+        pool.push_binop(OP);
+
+        pool.push_literal(7.5);
+        pool.push_literal(2);
+        pool.push_binop(OP);
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Int(1), Value::Float(1.5)]);
+    }
+}
+
+mod unaryop {
+    use super::*;
+
+    fn run_unaryop(operand: impl Into<Value>, op: UnaryOp) -> Result<Vec<Value>, Error> {
+        let mut pool = Pool::default();
+        pool.push_literal(operand);
+        pool.push_unaryop(op);
+        vm::create_and_run(&pool)
+    }
+
+    #[test]
+    fn neg() {
+        assert_eq!(run_unaryop(3, UnaryOp::Neg), Ok(vec![Value::Int(-3)]));
+        assert_eq!(run_unaryop(-1.5, UnaryOp::Neg), Ok(vec![Value::Float(1.5)]));
+        assert_eq!(
+            run_unaryop(i64::MIN, UnaryOp::Neg),
+            Ok(vec![Value::big(-&BigInt::from(i64::MIN))])
+        );
+        assert_eq!(
+            run_unaryop("str", UnaryOp::Neg),
+            Err(Error::UnsupportedOperand {
+                op: "Neg",
+                operand: "str"
+            })
+        );
+    }
+
+    #[test]
+    fn not() {
+        assert_eq!(run_unaryop(0, UnaryOp::Not), Ok(vec![Value::Int(1)]));
+        assert_eq!(run_unaryop(7, UnaryOp::Not), Ok(vec![Value::Int(0)]));
+        assert_eq!(run_unaryop(0.0, UnaryOp::Not), Ok(vec![Value::Int(1)]));
+        assert_eq!(
+            run_unaryop(Value::list(vec![]), UnaryOp::Not),
+            Ok(vec![Value::Int(1)])
+        );
+    }
+
+    #[test]
+    fn bit_not() {
+        assert_eq!(run_unaryop(0, UnaryOp::BitNot), Ok(vec![Value::Int(-1)]));
+        assert_eq!(run_unaryop(-6, UnaryOp::BitNot), Ok(vec![Value::Int(5)]));
+        assert_eq!(
+            run_unaryop(1.0, UnaryOp::BitNot),
+            Err(Error::UnsupportedOperand {
+                op: "BitNot",
+                operand: "float"
+            })
+        );
+    }
+}
+
+mod bitwise {
+    use super::*;
+
+    fn run_binop(lhs: impl Into<Value>, rhs: impl Into<Value>, op: BinOp) -> Result<Value, Error> {
+        let mut pool = Pool::default();
+        pool.push_literal(lhs);
+        pool.push_literal(rhs);
+        pool.push_binop(op);
+        vm::create_and_run(&pool).map(|mut stack| stack.pop().unwrap())
+    }
+
+    #[test]
+    fn logic() {
+        assert_eq!(
+            run_binop(0b1100, 0b1010, BinOp::BitAnd),
+            Ok(Value::Int(0b1000))
+        );
+        assert_eq!(
+            run_binop(0b1100, 0b1010, BinOp::BitOr),
+            Ok(Value::Int(0b1110))
+        );
+        assert_eq!(
+            run_binop(0b1100, 0b1010, BinOp::BitXor),
+            Ok(Value::Int(0b0110))
+        );
+        assert_eq!(run_binop(-1, 0xFF, BinOp::BitAnd), Ok(Value::Int(0xFF)));
+    }
+
+    #[test]
+    fn shifts() {
+        assert_eq!(run_binop(1, 4, BinOp::Shl), Ok(Value::Int(16)));
+        assert_eq!(run_binop(-1, 63, BinOp::Shl), Ok(Value::Int(i64::MIN)));
+        assert_eq!(run_binop(-5, 60, BinOp::Shl), Ok(Value::Int(-5 << 60)));
+        assert_eq!(run_binop(-16, 2, BinOp::Shr), Ok(Value::Int(-4)));
+        assert_eq!(run_binop(1, 64, BinOp::Shl), Err(Error::InvalidShift(64)));
+        assert_eq!(run_binop(1, -1, BinOp::Shr), Err(Error::InvalidShift(-1)));
+    }
+
+    #[test]
+    fn pow() {
+        assert_eq!(run_binop(3, 4, BinOp::Pow), Ok(Value::Int(81)));
+        assert_eq!(run_binop(2, -1, BinOp::Pow), Ok(Value::Float(0.5)));
+        assert_eq!(
+            run_binop(2.0, 0.5, BinOp::Pow),
+            Ok(Value::Float(2f64.sqrt()))
+        );
+        assert_eq!(
+            run_binop(2, 63, BinOp::Pow),
+            Ok(Value::big(BigInt::from(2).pow(63)))
+        );
+        assert_eq!(run_binop(-1, i64::MAX, BinOp::Pow), Ok(Value::Int(-1)));
+    }
+
+    #[test]
+    fn unsupported_operands() {
+        assert_eq!(
+            run_binop(1.0, 1, BinOp::BitOr),
+            Err(Error::UnsupportedOperands {
+                op: BinOp::BitOr,
+                lhs: "float",
+                rhs: "int"
+            })
+        );
+        assert_eq!(
+            run_binop("a", 2, BinOp::Pow),
+            Err(Error::UnsupportedOperands {
+                op: BinOp::Pow,
+                lhs: "str",
+                rhs: "int"
+            })
+        );
+    }
+}
+
+mod arithmetic_mode {
+    use super::*;
+
+    fn run_in(mode: ArithmeticMode, lhs: i64, rhs: i64, op: BinOp) -> Result<Vec<Value>, Error> {
+        let mut pool = Pool::default();
+        pool.push_literal(lhs);
+        pool.push_literal(rhs);
+        pool.push_binop(op);
+        let mut vm: vm::Vm = vm::Vm::new(pool.as_bytes(), &pool.constants);
+        vm.mode = mode;
+        vm.run()?;
+        Ok(vm.stack)
+    }
+
+    #[test]
+    fn checked() {
+        for (lhs, rhs, op) in [
+            (i64::MAX, 1, BinOp::Add),
+            (i64::MIN, 1, BinOp::Sub),
+            (i64::MAX, 2, BinOp::Mul),
+            (i64::MIN, -1, BinOp::Div),
+            (3, 40, BinOp::Pow),
+            (1, 63, BinOp::Shl),
+            (i64::MAX, 1, BinOp::Shl),
+            (i64::MIN, 1, BinOp::Shl),
+        ] {
+            assert_eq!(
+                run_in(ArithmeticMode::Checked, lhs, rhs, op),
+                Err(Error::IntegerOverflow)
+            );
+        }
+    }
+
+    #[test]
+    fn remainder_never_overflows() {
+        for mode in [
+            ArithmeticMode::Checked,
+            ArithmeticMode::Wrapping,
+            ArithmeticMode::Saturating,
+            ArithmeticMode::Promote,
+            ArithmeticMode::Unbounded,
+        ] {
+            assert_eq!(
+                run_in(mode, i64::MIN, -1, BinOp::Mod),
+                Ok(vec![Value::Int(0)]),
+                "{mode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn wrapping() {
+        let mode = ArithmeticMode::Wrapping;
+        assert_eq!(
+            run_in(mode, i64::MAX, 1, BinOp::Add),
+            Ok(vec![Value::Int(i64::MIN)])
+        );
+        assert_eq!(
+            run_in(mode, i64::MIN, 1, BinOp::Sub),
+            Ok(vec![Value::Int(i64::MAX)])
+        );
+        assert_eq!(
+            run_in(mode, i64::MIN, -1, BinOp::Div),
+            Ok(vec![Value::Int(i64::MIN)])
+        );
+        assert_eq!(
+            run_in(mode, i64::MIN, -1, BinOp::Mod),
+            Ok(vec![Value::Int(0)])
+        );
+        assert_eq!(run_in(mode, 2, 64, BinOp::Pow), Ok(vec![Value::Int(0)]));
+        assert_eq!(
+            run_in(mode, i64::MAX, 1, BinOp::Shl),
+            Ok(vec![Value::Int(-2)])
+        );
+        assert_eq!(
+            run_in(mode, 3, 63, BinOp::Shl),
+            Ok(vec![Value::Int(i64::MIN)])
+        );
+        assert_eq!(
+            run_in(mode, 3, 40, BinOp::Pow),
+            Ok(vec![Value::Int(3i64.wrapping_pow(40))])
+        );
+    }
+
+    #[test]
+    fn saturating() {
+        let mode = ArithmeticMode::Saturating;
+        assert_eq!(
+            run_in(mode, i64::MAX, 1, BinOp::Add),
+            Ok(vec![Value::Int(i64::MAX)])
+        );
+        assert_eq!(
+            run_in(mode, i64::MIN, 1, BinOp::Sub),
+            Ok(vec![Value::Int(i64::MIN)])
+        );
+        assert_eq!(
+            run_in(mode, i64::MIN, 2, BinOp::Mul),
+            Ok(vec![Value::Int(i64::MIN)])
+        );
+        assert_eq!(
+            run_in(mode, -3, 41, BinOp::Pow),
+            Ok(vec![Value::Int(i64::MIN)])
+        );
+        assert_eq!(
+            run_in(mode, i64::MAX, 1, BinOp::Shl),
+            Ok(vec![Value::Int(i64::MAX)])
+        );
+        assert_eq!(
+            run_in(mode, -3, 62, BinOp::Shl),
+            Ok(vec![Value::Int(i64::MIN)])
+        );
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn promote() {
+        let mode = ArithmeticMode::Promote;
+        assert_eq!(
+            run_in(mode, i64::MAX, 1, BinOp::Add),
+            Ok(vec![Value::Float(i64::MAX as f64 + 1.0)])
+        );
+        assert_eq!(
+            run_in(mode, 10, 20, BinOp::Pow),
+            Ok(vec![Value::Float(1e20)])
+        );
+        assert_eq!(
+            run_in(mode, 1, 63, BinOp::Shl),
+            Ok(vec![Value::Float(2f64.powi(63))])
+        );
+        assert_eq!(run_in(mode, 2, 3, BinOp::Mul), Ok(vec![Value::Int(6)]));
+    }
+
+    #[test]
+    fn neg() {
+        let mut pool = Pool::default();
+        pool.push_literal(i64::MIN);
+        pool.push_unaryop(UnaryOp::Neg);
+        let mut vm: vm::Vm = vm::Vm::new(pool.as_bytes(), &pool.constants);
+        vm.mode = ArithmeticMode::Saturating;
+        vm.run().unwrap();
+        assert_eq!(vm.stack, vec![Value::Int(i64::MAX)]);
+    }
+}
+
+mod bigint {
+    use super::*;
+
+    fn big(str: &str) -> Value {
+        Value::big(str.parse().unwrap())
+    }
+
+    fn run(lhs: Value, rhs: Value, op: BinOp) -> Value {
+        let mut pool = Pool::default();
+        pool.push_literal(lhs);
+        pool.push_literal(rhs);
+        pool.push_binop(op);
+        vm::create_and_run(&pool).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn promotes_and_demotes() {
+        let mut pool = Pool::default();
+        pool.push_literal(i64::MAX);
+        pool.push_literal(1);
+        pool.push_binop(BinOp::Add);
+        pool.push_dup();
+        pool.push_literal(1);
+        pool.push_binop(BinOp::Sub);
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(
+            stack,
+            vec![big("9223372036854775808"), Value::Int(i64::MAX)]
+        );
+    }
+
+    #[test]
+    fn factorial() {
+        let mut pool = Pool::default();
+        pool.push_literal(1);
+        for n in 1..=30 {
+            pool.push_literal(n);
+            pool.push_binop(BinOp::Mul);
+        }
+        pool.push_dup();
+        pool.push_literal(big("265252859812191058636308480000000"));
+        pool.push_binop(BinOp::Div);
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(
+            stack,
+            vec![big("265252859812191058636308480000000"), Value::Int(1)]
+        );
+    }
+
+    #[test]
+    fn mixed_operands() {
+        let huge = big("100000000000000000000");
+        assert_eq!(
+            run(huge.clone(), Value::Float(0.5), BinOp::Mul),
+            Value::Float(5e19)
+        );
+        assert_eq!(run(huge.clone(), Value::Int(7), BinOp::Mod), Value::Int(2));
+        assert_eq!(
+            run(huge.clone(), Value::Int(i64::MAX), BinOp::GT),
+            Value::Int(1)
+        );
+        assert_eq!(run(huge.clone(), huge.clone(), BinOp::Eq), Value::Int(1));
+        assert_eq!(run(huge.clone(), Value::Int(-1), BinOp::BitAnd), huge);
+        assert_eq!(
+            run(huge.clone(), Value::Int(60), BinOp::Shr),
+            Value::Int(86)
+        );
+        assert_eq!(
+            run(huge, Value::Int(2), BinOp::Shl),
+            big("400000000000000000000")
+        );
+    }
+
+    #[test]
+    fn shl_promotes() {
+        // The same shift gives the same number whether the operand is stored
+        // as an `Int` or a `BigInt`.
+        assert_eq!(
+            run(Value::Int(i64::MAX), Value::Int(1), BinOp::Shl),
+            big("18446744073709551614")
+        );
+        assert_eq!(
+            run(Value::Int(1), Value::Int(63), BinOp::Shl),
+            big("9223372036854775808")
+        );
+        let two_63 = run(Value::Int(1), Value::Int(63), BinOp::Shl);
+        assert_eq!(
+            run(two_63, Value::Int(1), BinOp::Shl),
+            big("18446744073709551616")
+        );
+        assert_eq!(
+            run(Value::Int(i64::MIN), Value::Int(1), BinOp::Shl),
+            big("-18446744073709551616")
+        );
+    }
+
+    #[test]
+    fn modes() {
+        let mut pool = Pool::default();
+        pool.push_literal(i64::MAX);
+        pool.push_literal(i64::MAX);
+        pool.push_binop(BinOp::Mul);
+        let mut vm: vm::Vm = vm::Vm::new(pool.as_bytes(), &pool.constants);
+        vm.mode = ArithmeticMode::Checked;
+        assert_eq!(vm.run(), Err(Error::IntegerOverflow));
+
+        let mut pool = Pool::default();
+        pool.push_literal(2);
+        pool.push_literal(1 << 30);
+        pool.push_binop(BinOp::Pow);
+        assert_eq!(vm::create_and_run(&pool), Err(Error::IntegerOverflow));
+
+        let mut pool = Pool::default();
+        pool.push_literal(big("100000000000000000000"));
+        pool.push_literal(0);
+        pool.push_binop(BinOp::Mod);
+        assert_eq!(vm::create_and_run(&pool), Err(Error::DivisionByZero));
+    }
+
+    #[test]
+    fn disassembly() {
+        let mut pool = Pool::default();
+        pool.push_literal(big("-123456789012345678901234567890"));
+        assert!(pool
+            .to_string()
+            .contains("(BigInt(-123456789012345678901234567890))"));
+    }
+}
+
+mod compare {
+    use super::*;
+
+    fn compare(lhs: impl Into<Value>, rhs: impl Into<Value>, op: BinOp) -> i64 {
+        match Value::run_binop(lhs.into(), rhs.into(), op, ArithmeticMode::Checked).unwrap() {
+            Value::Int(int) => int,
+            value => panic!("{value:?}"),
+        }
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(compare(1, 2, BinOp::LT), 1);
+        assert_eq!(compare(2, 2.0, BinOp::LE), 1);
+        assert_eq!(compare(2.5, 2, BinOp::GT), 1);
+        assert_eq!(compare(2, 3, BinOp::GE), 0);
+        assert_eq!(compare(2, 2.0, BinOp::Eq), 1);
+        assert_eq!(compare(f64::NAN, f64::NAN, BinOp::Eq), 0);
+        assert_eq!(compare(f64::NAN, f64::NAN, BinOp::Ne), 1);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(compare("abc", "abd", BinOp::LT), 1);
+        assert_eq!(compare("abc", "abc", BinOp::Eq), 1);
+        assert_eq!(compare("abc", 1, BinOp::Eq), 0);
+        assert_eq!(compare("abc", 1, BinOp::Ne), 1);
+        assert_eq!(
+            Value::run_binop(
+                "abc".into(),
+                Value::Int(1),
+                BinOp::LT,
+                ArithmeticMode::Checked
+            ),
+            Err(Error::UnsupportedOperands {
+                op: BinOp::LT,
+                lhs: "str",
+                rhs: "int"
+            })
+        );
+    }
+}
+
 mod test_jump {
     use super::*;
 
@@ -237,7 +737,7 @@ mod test_jump {
         pool.patch_jump(jump);
         pool.push_literal(4);
 
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(stack, vec![Value::Int(1), Value::Int(4)]);
     }
 
@@ -253,7 +753,7 @@ mod test_jump {
         pool.push_pop_jump_if_false(flag);
 
         eprintln!("{pool}");
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(stack, vec![Value::Int(-1)]);
     }
 }
@@ -271,13 +771,10 @@ mod test_pop_jump_if_false {
         pool.push_literal(", World!");
 
         eprintln!("{pool}");
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(
             stack,
-            vec![
-                Value::Str(Cow::Borrowed("Hello")),
-                Value::Str(Cow::Borrowed(", World!"))
-            ]
+            vec![Value::Str("Hello".into()), Value::Str(", World!".into())]
         );
     }
 
@@ -291,8 +788,8 @@ mod test_pop_jump_if_false {
         });
         pool.push_literal(", World!");
 
-        let stack = vm::create_and_run(&pool);
-        assert_eq!(stack, vec![Value::Str(Cow::Borrowed(", World!"))]);
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Str(", World!".into())]);
     }
 
     #[test]
@@ -309,8 +806,8 @@ mod test_pop_jump_if_false {
             },
         );
 
-        let stack = vm::create_and_run(&pool);
-        assert_eq!(stack, vec![Value::Str(Cow::Borrowed("if"))]);
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Str("if".into())]);
     }
 
     #[test]
@@ -327,31 +824,381 @@ mod test_pop_jump_if_false {
             },
         );
 
-        let stack = vm::create_and_run(&pool);
-        assert_eq!(stack, vec![Value::Str(Cow::Borrowed("else"))]);
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Str("else".into())]);
     }
 }
 
-mod loops {
+mod truthiness {
     use super::*;
 
-    #[test]
-    fn test_loop() {
+    fn truthy(value: Value) -> bool {
         let mut pool = Pool::default();
-        pool.push_literal(4);
+        pool.push_const(value);
+        pool.push_if_or_else(
+            |if_body| {
+                if_body.push_literal(1);
+            },
+            |else_body| {
+                else_body.push_literal(0);
+            },
+        );
+        vm::create_and_run(&pool).unwrap() == vec![Value::Int(1)]
+    }
 
-        let mut jump = 0;
-        pool.push_loop(|body| {
-            body.push_literal(1);
-            body.push_binop(BinOp::Sub);
-            body.push_dup();
-            jump = body.push_pop_jump_if_false(0);
-            body.push_dup();
-        });
+    #[test]
+    fn strings() {
+        assert!(truthy("hello".into()));
+        assert!(!truthy("".into()));
+    }
+
+    #[test]
+    fn numbers() {
+        assert!(truthy((-1).into()));
+        assert!(!truthy(0.into()));
+        assert!(truthy(0.5.into()));
+        assert!(truthy(f64::NAN.into()));
+        assert!(!truthy((-0.0).into()));
+        assert!(truthy(Value::big(BigInt::from(i64::MAX).pow(2))));
+    }
+
+    #[test]
+    fn collections() {
+        assert!(!truthy(Value::list(vec![])));
+        assert!(truthy(Value::list(vec![0.into()])));
+        assert!(!truthy(Value::map(crate::map::Map::default())));
+    }
+
+    #[test]
+    fn pop_jump_if_true() {
+        let mut pool = Pool::default();
+        pool.push_literal("skip");
+        let jump = pool.push_pop_jump_if_true(0);
+        pool.push_literal("not skipped");
+        pool.patch_jump(jump);
+        pool.push_literal("");
+        let jump = pool.push_pop_jump_if_true(0);
+        pool.push_literal("after");
+        pool.patch_jump(jump);
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Str("after".into())]);
+    }
+
+    fn short_circuit(lhs: Value, rhs: Value, or: bool) -> Vec<Value> {
+        let mut pool = Pool::default();
+        pool.push_const(lhs);
+        let jump = if or {
+            pool.push_jump_if_true_or_pop(0)
+        } else {
+            pool.push_jump_if_false_or_pop(0)
+        };
+        pool.push_const(rhs);
+        pool.patch_jump(jump);
+        pool.relax();
+        vm::create_and_run(&pool).unwrap()
+    }
+
+    #[test]
+    fn and() {
+        assert_eq!(short_circuit(0.into(), 5.into(), false), vec![0.into()]);
+        assert_eq!(short_circuit(3.into(), 5.into(), false), vec![5.into()]);
+        assert_eq!(short_circuit("".into(), "x".into(), false), vec!["".into()]);
+    }
+
+    #[test]
+    fn or() {
+        assert_eq!(short_circuit(0.into(), 5.into(), true), vec![5.into()]);
+        assert_eq!(short_circuit(3.into(), 5.into(), true), vec![3.into()]);
+        assert_eq!(short_circuit("".into(), "x".into(), true), vec!["x".into()]);
+    }
+
+    #[test]
+    fn empty_stack() {
+        let mut pool = Pool::default();
+        pool.push_jump_if_false_or_pop(0);
+        assert_eq!(vm::create_and_run(&pool), Err(Error::StackUnderflow));
+    }
+}
+
+mod stack_ops {
+    use super::*;
+
+    fn run(push: impl FnOnce(&mut Pool)) -> Result<Vec<Value>, Error> {
+        let mut pool = Pool::default();
+        for int in 1..=3 {
+            pool.push_literal(int);
+        }
+        push(&mut pool);
+        eprintln!("{pool}");
+        vm::create_and_run(&pool)
+    }
+
+    fn ints(ints: &[i64]) -> Vec<Value> {
+        ints.iter().copied().map(Value::Int).collect()
+    }
+
+    #[test]
+    fn pop() {
+        assert_eq!(run(Pool::push_pop), Ok(ints(&[1, 2])));
+    }
+
+    #[test]
+    fn swap() {
+        assert_eq!(run(Pool::push_swap), Ok(ints(&[1, 3, 2])));
+    }
+
+    #[test]
+    fn rot3() {
+        assert_eq!(run(Pool::push_rot3), Ok(ints(&[3, 1, 2])));
+    }
+
+    #[test]
+    fn over() {
+        assert_eq!(run(Pool::push_over), Ok(ints(&[1, 2, 3, 2])));
+    }
+
+    #[test]
+    fn pop_n() {
+        assert_eq!(run(|pool| pool.push_pop_n(2)), Ok(ints(&[1])));
+        assert_eq!(run(|pool| pool.push_pop_n(0)), Ok(ints(&[1, 2, 3])));
+        assert_eq!(run(|pool| pool.push_pop_n(3)), Ok(vec![]));
+    }
+
+    #[test]
+    fn underflow() {
+        assert_eq!(run(|pool| pool.push_pop_n(4)), Err(Error::StackUnderflow));
+        let mut pool = Pool::default();
+        pool.push_literal(1);
+        pool.push_literal(2);
+        pool.push_rot3();
+        assert_eq!(vm::create_and_run(&pool), Err(Error::StackUnderflow));
+    }
+
+    #[test]
+    fn balanced_loop() {
+        // Sums 3 + 2 + 1 keeping only the total and the counter on the stack.
+        let mut pool = Pool::default();
+        pool.push_literal(0);
+        pool.push_literal(3);
+        pool.push_while_loop(
+            |condition| {
+                condition.push_dup();
+            },
+            |body| {
+                body.push_swap();
+                body.push_over();
+                body.push_binop(BinOp::Add);
+                body.push_swap();
+                body.push_literal(1);
+                body.push_binop(BinOp::Sub);
+            },
+        );
+        pool.push_pop();
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, ints(&[6]));
+    }
+}
+
+mod print {
+    use super::*;
+    use crate::output::{Buffer, Output};
+    use crate::packed::Packed;
+    use std::io::{self, Write};
+
+    #[test]
+    fn lines() {
+        let mut pool = Pool::default();
+        pool.push_literal(3);
+        pool.push_while_loop(
+            |condition| {
+                condition.push_dup();
+            },
+            |body| {
+                body.push_dup();
+                body.push_print();
+                body.push_literal(1);
+                body.push_binop(BinOp::Sub);
+            },
+        );
+        pool.push_literal("liftoff");
+        pool.push_print();
+
+        let buffer = Buffer::default();
+        let mut vm: vm::Vm<Packed> = vm::Vm::new(pool.as_bytes(), &pool.constants);
+        vm.output = Output::new(buffer.clone());
+        vm.run().unwrap();
+        assert_eq!(buffer.contents(), "3\n2\n1\nliftoff\n");
+    }
+
+    struct Closed;
+
+    impl Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut pool = Pool::default();
+        pool.push_print();
+        assert_eq!(vm::create_and_run(&pool), Err(Error::StackUnderflow));
+
+        let mut pool = Pool::default();
+        pool.push_literal(1);
+        pool.push_print();
+        let mut vm: vm::Vm = vm::Vm::new(pool.as_bytes(), &pool.constants);
+        vm.output = Output::new(Closed);
+        assert_eq!(vm.run(), Err(Error::Output(io::ErrorKind::BrokenPipe)));
+    }
+}
+
+mod conversions {
+    use super::*;
+    use crate::instr::Instr;
+
+    fn convert(value: Value, push: impl FnOnce(&mut Pool)) -> Result<Value, Error> {
+        let mut pool = Pool::default();
+        pool.push_const(value);
+        push(&mut pool);
+        vm::create_and_run(&pool).map(|mut stack| stack.pop().unwrap())
+    }
+
+    #[test]
+    fn to_str() {
+        let to_str = |value| convert(value, Pool::push_to_str).unwrap();
+        assert_eq!(to_str(42.into()), "42".into());
+        assert_eq!(to_str(2.0.into()), "2.0".into());
+        assert_eq!(to_str("as is".into()), "as is".into());
+        assert_eq!(
+            to_str(Value::list(vec!["a".into(), 1.into()])),
+            "[\"a\", 1]".into()
+        );
+    }
+
+    #[test]
+    fn to_int() {
+        let to_int = |value| convert(value, Pool::push_to_int);
+        assert_eq!(to_int(" -42\n".into()), Ok(Value::Int(-42)));
+        assert_eq!(to_int("+7".into()), Ok(Value::Int(7)));
+        assert_eq!(
+            to_int("18446744073709551616".into()),
+            Ok(Value::big(BigInt::from(2).pow(64)))
+        );
+        assert_eq!(to_int(3.9.into()), Ok(Value::Int(3)));
+        assert_eq!(to_int((-3.9).into()), Ok(Value::Int(-3)));
+        assert_eq!(
+            to_int(1e19.into()),
+            Ok(Value::big("10000000000000000000".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn to_int_errors() {
+        let to_int = |value| convert(value, Pool::push_to_int);
+        assert!(matches!(
+            to_int(f64::NAN.into()),
+            Err(Error::NonFinite(float)) if float.is_nan()
+        ));
+        assert_eq!(
+            to_int(f64::INFINITY.into()),
+            Err(Error::NonFinite(f64::INFINITY))
+        );
+        assert_eq!(
+            to_int("1.5".into()),
+            Err(Error::Parse {
+                text: "1.5".into(),
+                into: "int"
+            })
+        );
+        assert_eq!(
+            to_int("".into()),
+            Err(Error::Parse {
+                text: "".into(),
+                into: "int"
+            })
+        );
+        assert_eq!(
+            to_int(Value::list(vec![])),
+            Err(Error::UnsupportedOperand {
+                op: "ToInt",
+                operand: "list"
+            })
+        );
+    }
+
+    #[test]
+    fn to_float() {
+        let to_float = |value| convert(value, Pool::push_to_float);
+        assert_eq!(to_float(3.into()), Ok(Value::Float(3.0)));
+        assert_eq!(to_float(" 1.5e3 ".into()), Ok(Value::Float(1500.0)));
+        assert_eq!(to_float("-inf".into()), Ok(Value::Float(f64::NEG_INFINITY)));
+        assert_eq!(
+            to_float("one".into()),
+            Err(Error::Parse {
+                text: "one".into(),
+                into: "float"
+            })
+        );
+    }
+
+    #[test]
+    fn format() {
+        let mut pool = Pool::default();
+        pool.push_literal("x");
+        pool.push_literal(1.5);
+        pool.push_format("{} = {}");
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![Value::Str("x = 1.5".into())]);
+
+        let mut pool = Pool::default();
+        pool.push_literal(1);
+        pool.push_format("{} and {}");
+        assert_eq!(vm::create_and_run(&pool), Err(Error::StackUnderflow));
+
+        let mut pool = Pool::default();
+        pool.push_format("{");
+        assert_eq!(
+            vm::create_and_run(&pool),
+            Err(Error::InvalidFormat("{".into()))
+        );
+
+        let pool = Pool::from_instrs(&[Instr::Format(0)], vec![1.into()]);
+        assert_eq!(
+            vm::create_and_run(&pool),
+            Err(Error::UnsupportedOperand {
+                op: "Format",
+                operand: "int"
+            })
+        );
+    }
+}
+
+mod loops {
+    use super::*;
+
+    #[test]
+    fn test_loop() {
+        let mut pool = Pool::default();
+        pool.push_literal(4);
+
+        let mut jump = 0;
+        pool.push_loop(|body| {
+            body.push_literal(1);
+            body.push_binop(BinOp::Sub);
+            body.push_dup();
+            jump = body.push_pop_jump_if_false(0);
+            body.push_dup();
+        });
         pool.patch_jump(jump);
 
         eprintln!("{pool}");
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(
             stack,
             vec![Value::Int(3), Value::Int(2), Value::Int(1), Value::Int(0)]
@@ -375,10 +1222,368 @@ mod loops {
         );
 
         eprintln!("{pool}");
-        let stack = vm::create_and_run(&pool);
+        let stack = vm::create_and_run(&pool).unwrap();
         assert_eq!(
             stack,
             vec![Value::Int(3), Value::Int(2), Value::Int(1), Value::Int(0)]
         );
     }
 }
+
+mod errors {
+    use super::*;
+
+    #[test]
+    fn stack_underflow() {
+        let mut pool = Pool::default();
+        pool.push_dup();
+
+        assert_eq!(vm::create_and_run(&pool), Err(Error::StackUnderflow));
+    }
+
+    #[test]
+    fn invalid_op_code() {
+        let vm_bytes = [u8::MAX];
+        let mut vm: vm::Vm = vm::Vm::new(&vm_bytes, &[]);
+
+        assert_eq!(vm.run(), Err(Error::InvalidOpCode(u8::MAX)));
+    }
+
+    #[test]
+    fn unsupported_operands() {
+        let mut pool = Pool::default();
+        pool.push_literal(1.5);
+        pool.push_literal("str");
+        pool.push_binop(BinOp::Mul);
+
+        assert_eq!(
+            vm::create_and_run(&pool),
+            Err(Error::UnsupportedOperands {
+                op: BinOp::Mul,
+                lhs: "float",
+                rhs: "str"
+            })
+        );
+    }
+}
+mod list {
+    use super::*;
+
+    fn ints(ints: &[i64]) -> Value {
+        Value::list(ints.iter().copied().map(Value::Int).collect())
+    }
+    fn push_list(pool: &mut Pool, items: &[i64]) {
+        for &item in items {
+            pool.push_literal(item);
+        }
+        pool.push_build_list(items.len().try_into().unwrap());
+    }
+
+    #[test]
+    fn build() {
+        let mut pool = Pool::default();
+        push_list(&mut pool, &[1, 2, 3]);
+        push_list(&mut pool, &[]);
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![ints(&[1, 2, 3]), ints(&[])]);
+    }
+
+    #[test]
+    fn index_and_len() {
+        let mut pool = Pool::default();
+        push_list(&mut pool, &[10, 20, 30]);
+        pool.push_dup();
+        pool.push_len();
+        pool.push_literal(1);
+        pool.push_binop(BinOp::Sub);
+        pool.push_index();
+
+        assert_eq!(vm::create_and_run(&pool).unwrap(), vec![Value::Int(30)]);
+    }
+
+    #[test]
+    fn store_and_append() {
+        let mut pool = Pool::default();
+        push_list(&mut pool, &[1, 2]);
+        pool.push_dup();
+        pool.push_literal(0);
+        pool.push_literal(7);
+        pool.push_store_index();
+        pool.push_literal(3);
+        pool.push_append();
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![ints(&[7, 2, 3])]);
+    }
+
+    #[test]
+    fn aliasing() {
+        let mut pool = Pool::default();
+        push_list(&mut pool, &[1]);
+        pool.push_dup();
+        pool.push_literal(2);
+        pool.push_append();
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![ints(&[1, 2]), ints(&[1, 2])]);
+    }
+
+    #[test]
+    fn binops() {
+        let mut pool = Pool::default();
+        push_list(&mut pool, &[1, 2]);
+        push_list(&mut pool, &[3]);
+        pool.push_binop(BinOp::Add);
+        pool.push_literal(2);
+        pool.push_binop(BinOp::Mul);
+        pool.push_dup();
+        push_list(&mut pool, &[1, 2, 3, 1, 2, 3]);
+        pool.push_binop(BinOp::Eq);
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![ints(&[1, 2, 3, 1, 2, 3]), Value::Int(1)]);
+    }
+
+    #[test]
+    fn repetition_limit() {
+        let limit = Err(Error::SizeLimit(Value::MAX_REPEAT_LEN));
+        for count in [
+            i64::MAX,
+            1 << 40,
+            i64::try_from(Value::MAX_REPEAT_LEN / 2 + 1).unwrap(),
+        ] {
+            let mut pool = Pool::default();
+            push_list(&mut pool, &[1, 2]);
+            pool.push_literal(count);
+            pool.push_binop(BinOp::Mul);
+            assert_eq!(vm::create_and_run(&pool), limit, "list * {count}");
+
+            let mut pool = Pool::default();
+            pool.push_literal(count);
+            pool.push_literal("ab");
+            pool.push_binop(BinOp::Mul);
+            assert_eq!(vm::create_and_run(&pool), limit, "{count} * str");
+        }
+
+        let mut pool = Pool::default();
+        push_list(&mut pool, &[]);
+        pool.push_literal(i64::MAX);
+        pool.push_binop(BinOp::Mul);
+        pool.push_literal("");
+        pool.push_literal(i64::MAX);
+        pool.push_binop(BinOp::Mul);
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(stack, vec![ints(&[]), Value::Str("".into())]);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        for index in [-1, 2] {
+            let mut pool = Pool::default();
+            push_list(&mut pool, &[1, 2]);
+            pool.push_literal(index);
+            pool.push_index();
+
+            assert_eq!(
+                vm::create_and_run(&pool),
+                Err(Error::IndexOutOfBounds { index, len: 2 })
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_operand() {
+        let mut pool = Pool::default();
+        pool.push_literal("str");
+        pool.push_len();
+
+        assert_eq!(
+            vm::create_and_run(&pool),
+            Err(Error::UnsupportedOperand {
+                op: "Len",
+                operand: "str"
+            })
+        );
+    }
+
+    #[test]
+    fn build_underflow() {
+        let mut pool = Pool::default();
+        pool.push_literal(1);
+        pool.push_build_list(2);
+
+        assert_eq!(vm::create_and_run(&pool), Err(Error::StackUnderflow));
+    }
+}
+
+mod map {
+    use super::*;
+    use crate::map::Key;
+
+    fn push_map(pool: &mut Pool, entries: &[(&str, i64)]) {
+        for &(key, value) in entries {
+            pool.push_literal(key);
+            pool.push_literal(value);
+        }
+        pool.push_build_map(entries.len().try_into().unwrap());
+    }
+
+    #[test]
+    fn get_and_set() {
+        let mut pool = Pool::default();
+        push_map(&mut pool, &[("a", 1), ("b", 2)]);
+        pool.push_dup();
+        pool.push_literal("a");
+        pool.push_literal(4);
+        pool.push_set_item();
+        pool.push_dup();
+        pool.push_literal("a");
+        pool.push_get_item();
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        let entries = [
+            (Key::Str("a".into()), Value::Int(4)),
+            (Key::Str("b".into()), Value::Int(2)),
+        ];
+        assert_eq!(
+            stack,
+            vec![Value::map(entries.into_iter().collect()), Value::Int(4)]
+        );
+    }
+
+    #[test]
+    fn keys_in_insertion_order() {
+        let mut pool = Pool::default();
+        push_map(&mut pool, &[("b", 1), ("a", 2)]);
+        pool.push_dup();
+        pool.push_literal("c");
+        pool.push_literal(3);
+        pool.push_set_item();
+        pool.push_dup();
+        pool.push_literal("b");
+        pool.push_literal(4);
+        pool.push_set_item();
+        pool.push_keys();
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        assert_eq!(
+            stack,
+            vec![Value::list(vec!["b".into(), "a".into(), "c".into()])]
+        );
+    }
+
+    #[test]
+    fn has_key() {
+        let mut pool = Pool::default();
+        push_map(&mut pool, &[("a", 1), ("a", 2)]);
+        pool.push_dup();
+        pool.push_literal("a");
+        pool.push_has_key();
+        push_map(&mut pool, &[]);
+        pool.push_literal("a");
+        pool.push_has_key();
+
+        let stack = vm::create_and_run(&pool).unwrap();
+        let entries = [(Key::Str("a".into()), Value::Int(2))];
+        assert_eq!(
+            stack,
+            vec![
+                Value::map(entries.into_iter().collect()),
+                Value::Int(1),
+                Value::Int(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn len() {
+        let mut pool = Pool::default();
+        push_map(&mut pool, &[("a", 1), ("b", 2)]);
+        pool.push_len();
+
+        assert_eq!(vm::create_and_run(&pool).unwrap(), vec![Value::Int(2)]);
+    }
+
+    #[test]
+    fn missing_key() {
+        let mut pool = Pool::default();
+        push_map(&mut pool, &[("a", 1)]);
+        pool.push_literal("b");
+        pool.push_get_item();
+
+        assert_eq!(
+            vm::create_and_run(&pool),
+            Err(Error::KeyNotFound(Key::Str("b".into())))
+        );
+    }
+
+    #[test]
+    fn float_key() {
+        let mut pool = Pool::default();
+        pool.push_literal(1.5);
+        pool.push_literal(1);
+        pool.push_build_map(1);
+
+        assert_eq!(
+            vm::create_and_run(&pool),
+            Err(Error::UnhashableKey("float"))
+        );
+    }
+}
+
+mod jump_width {
+    use super::*;
+    use crate::instr::{Instr, Target};
+    use crate::variable_length::bytecode::OpCode;
+
+    fn count_down(pool: &mut Pool, padding: usize) {
+        pool.push_literal(3);
+        pool.push_while_loop(
+            |pool| {
+                pool.push_dup();
+            },
+            |pool| {
+                for _ in 0..padding {
+                    pool.push_nop();
+                }
+                pool.push_literal(1);
+                pool.push_binop(BinOp::Sub);
+            },
+        );
+    }
+
+    #[test]
+    fn relax_picks_narrowest() {
+        for (padding, width) in [(0, 1), (150, 2), (70_000, 4)] {
+            let mut pool = Pool::default();
+            count_down(&mut pool, padding);
+            let before = pool.len();
+            pool.relax();
+
+            // The exit jump follows `LoadConst` and `Dup`, and both it and the
+            // jump back cover the whole padded body.
+            let exit = OpCode::of(&Instr::PopJumpIfFalse(Target(0)), width);
+            assert_eq!(pool.as_bytes()[6], exit as u8, "padding {padding}");
+            assert_eq!(
+                before - pool.len(),
+                2 * (OpCode::JUMP_SIZE - width as usize)
+            );
+            assert_eq!(vm::create_and_run(&pool).unwrap(), vec![Value::Int(0)]);
+        }
+    }
+
+    #[test]
+    fn widening_moves_targets() {
+        // The backward jump stays a byte while the forward one has to grow
+        // once the padding pushes its target past `i8::MAX`.
+        let mut instrs = vec![Instr::PopJumpIfFalse(Target(255)), Instr::Jump(Target(0))];
+        instrs.extend(std::iter::repeat_n(Instr::Nop, 253));
+        instrs.push(Instr::Nop);
+
+        let pool = Pool::from_instrs(&instrs, vec![]);
+        assert_eq!(pool.as_bytes()[0], OpCode::PopJumpIfFalse16 as u8);
+        assert_eq!(pool.as_bytes()[3], OpCode::Jump8 as u8);
+        assert_eq!(pool.decode().unwrap(), instrs);
+    }
+}
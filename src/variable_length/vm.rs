@@ -1,81 +1,245 @@
-use super::bytecode::{OpCode, Pool};
-use crate::{BinOp, Value};
+use super::bytecode::{read_offset, OpCode, Pool};
+use crate::{
+    format::Template, instr, output::Output, ArithmeticMode, BinOp, Error, Machine, Slot, UnaryOp,
+    Value,
+};
+use std::rc::Rc;
 
-#[must_use]
-pub fn create_and_run<'a>(pool: &'a Pool<'a>) -> Vec<Value<'a>> {
-    let mut vm = Vm::new(pool.as_bytes(), &pool.constants);
-    vm.run();
-    vm.stack
+pub fn create_and_run(pool: &Pool) -> Result<Vec<Value>, Error> {
+    let mut vm: Vm = Vm::new(pool.as_bytes(), &pool.constants);
+    vm.run()?;
+    Ok(vm.stack)
 }
 
+/// Runs on `Value`s by default, or on any other `Slot` such as `packed::Packed`.
 #[derive(Debug)]
-pub struct Vm<'a> {
-    pub bytes: &'a [u8],
-    pub constants: &'a [Value<'a>],
+pub struct Vm<V: Slot = Value> {
+    pub bytes: Rc<[u8]>,
+    pub constants: Rc<[V]>,
     pub head: usize,
-    pub stack: Vec<Value<'a>>,
+    pub stack: Vec<V>,
+    pub mode: ArithmeticMode,
+    /// Where `Print` writes.
+    pub output: Output,
 }
 
-impl<'a> Vm<'a> {
+impl<V: Slot> Vm<V> {
     #[must_use]
-    pub fn new(bytes: &'a [u8], constants: &'a [Value<'a>]) -> Self {
+    pub fn new(bytes: &[u8], constants: &[Value]) -> Self {
         Self {
-            bytes,
-            constants,
+            bytes: bytes.into(),
+            constants: constants.iter().cloned().map(V::from_value).collect(),
             head: 0,
             stack: vec![],
+            mode: ArithmeticMode::default(),
+            output: Output::default(),
         }
     }
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> Result<(), Error> {
         while self.head < self.bytes.len() {
-            self.run_next();
+            self.run_next()?;
         }
+        Ok(())
     }
-    pub fn run_next(&mut self) {
+    #[allow(clippy::too_many_lines)]
+    pub fn run_next(&mut self) -> Result<(), Error> {
         let op_code_byte = self.bytes[self.head];
         self.head += 1;
 
-        assert!(op_code_byte < OpCode::LEN as u8, "{op_code_byte:?}");
+        if op_code_byte >= OpCode::LEN as u8 {
+            return Err(Error::InvalidOpCode(op_code_byte));
+        }
         let op_code: OpCode = unsafe { std::mem::transmute(op_code_byte) };
         match op_code {
             OpCode::Dup => {
-                let top = self.stack.last().unwrap();
+                let top = self.stack.last().ok_or(Error::StackUnderflow)?;
                 self.stack.push(top.clone());
             }
+            OpCode::Pop => {
+                self.pop()?;
+            }
+            OpCode::Swap => self.top_mut(2)?.swap(0, 1),
+            OpCode::Rot3 => self.top_mut(3)?.rotate_right(1),
+            OpCode::Over => {
+                let second = self.top_mut(2)?[0].clone();
+                self.stack.push(second);
+            }
+            OpCode::PopN => {
+                let len = u32::from_le_bytes(self.read()) as usize;
+                self.head += 4;
+                self.split_top(len)?;
+            }
+            OpCode::Print => {
+                let value = self.pop()?;
+                self.output.print(&value.into_value())?;
+            }
+            OpCode::ToStr => {
+                let value = self.pop()?;
+                self.stack.push(V::to_str(value));
+            }
+            OpCode::ToInt => {
+                let value = self.pop()?;
+                self.stack.push(V::to_int(value)?);
+            }
+            OpCode::ToFloat => {
+                let value = self.pop()?;
+                self.stack.push(V::to_float(value)?);
+            }
+            OpCode::Format => {
+                let index = u32::from_le_bytes(self.read()) as usize;
+                self.head += 4;
+                let template = self
+                    .constants
+                    .get(index)
+                    .ok_or(Error::InvalidConstant(index))?;
+                let template = Template::try_from(&template.clone().into_value())?;
+                let args = self.split_top(template.holes())?;
+                let rendered = template.render(args.into_iter().map(Slot::into_value));
+                self.stack.push(V::from_value(rendered));
+            }
             OpCode::LoadConst => {
                 let index = u32::from_le_bytes(self.read()) as usize;
-                let constant = &self.constants[index];
-                self.stack.push(constant.clone());
+                let constant = self
+                    .constants
+                    .get(index)
+                    .ok_or(Error::InvalidConstant(index))?;
+                self.stack.push(V::load_const(constant));
                 self.head += 4;
             }
+            OpCode::BuildList => {
+                let len = u32::from_le_bytes(self.read()) as usize;
+                self.head += 4;
+                let items = self.split_top(len)?;
+                self.stack.push(V::build_list(items));
+            }
+            OpCode::Index => {
+                let index = self.pop()?;
+                let list = self.pop()?;
+                self.stack.push(V::index(list, index)?);
+            }
+            OpCode::StoreIndex => {
+                let value = self.pop()?;
+                let index = self.pop()?;
+                let list = self.pop()?;
+                V::store_index(list, index, value)?;
+            }
+            OpCode::Len => {
+                let list = self.pop()?;
+                self.stack.push(V::length(list)?);
+            }
+            OpCode::Append => {
+                let value = self.pop()?;
+                V::append(self.stack.last().ok_or(Error::StackUnderflow)?, value)?;
+            }
+            OpCode::BuildMap => {
+                let len = u32::from_le_bytes(self.read()) as usize;
+                self.head += 4;
+                let items = self.split_top(len.checked_mul(2).ok_or(Error::StackUnderflow)?)?;
+                self.stack.push(V::build_map(items)?);
+            }
+            OpCode::GetItem => {
+                let key = self.pop()?;
+                let map = self.pop()?;
+                self.stack.push(V::get_item(map, key)?);
+            }
+            OpCode::SetItem => {
+                let value = self.pop()?;
+                let key = self.pop()?;
+                let map = self.pop()?;
+                V::set_item(map, key, value)?;
+            }
+            OpCode::HasKey => {
+                let key = self.pop()?;
+                let map = self.pop()?;
+                self.stack.push(V::has_key(map, key)?);
+            }
+            OpCode::Keys => {
+                let map = self.pop()?;
+                self.stack.push(V::keys(map)?);
+            }
             OpCode::BinOp => {
-                let op_byte = self.bytes[self.head];
-                let op: BinOp = unsafe { std::mem::transmute(op_byte) };
+                let op = BinOp::try_from(self.bytes[self.head])?;
 
                 self.head += 1;
 
-                let rhs = self.stack.pop().expect("Failed to pop stack");
-                let lhs = self.stack.pop().expect("Failed to pop stack");
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
 
-                let new_value = Value::run_binop(lhs, rhs, op);
+                let new_value = V::run_binop(lhs, rhs, op, self.mode)?;
                 self.stack.push(new_value);
             }
-            OpCode::Jump => {
-                let jump_pos = usize::from_le_bytes(self.read());
-                self.head = jump_pos;
+            OpCode::UnaryOp => {
+                let op = UnaryOp::try_from(self.bytes[self.head])?;
+                self.head += 1;
+
+                let operand = self.pop()?;
+                self.stack.push(V::run_unaryop(operand, op, self.mode)?);
+            }
+            OpCode::Jump8 | OpCode::Jump16 | OpCode::Jump32 => {
+                self.head = self.read_target(op_code)?;
+            }
+            OpCode::PopJumpIfFalse8 | OpCode::PopJumpIfFalse16 | OpCode::PopJumpIfFalse32 => {
+                let jump_pos = self.read_target(op_code)?;
+                self.head += op_code.size().unwrap() as usize;
+                let value = self.pop()?;
+                if !value.is_truthy() {
+                    self.head = jump_pos;
+                }
+            }
+            OpCode::PopJumpIfTrue8 | OpCode::PopJumpIfTrue16 | OpCode::PopJumpIfTrue32 => {
+                let jump_pos = self.read_target(op_code)?;
+                self.head += op_code.size().unwrap() as usize;
+                let value = self.pop()?;
+                if value.is_truthy() {
+                    self.head = jump_pos;
+                }
+            }
+            OpCode::JumpIfFalseOrPop8 | OpCode::JumpIfFalseOrPop16 | OpCode::JumpIfFalseOrPop32 => {
+                let jump_pos = self.read_target(op_code)?;
+                self.head += op_code.size().unwrap() as usize;
+                let top = self.stack.last().ok_or(Error::StackUnderflow)?;
+                if top.is_truthy() {
+                    self.stack.pop();
+                } else {
+                    self.head = jump_pos;
+                }
             }
-            OpCode::PopJumpIfFalse => {
-                let jump_pos = usize::from_le_bytes(self.read());
-                self.head += OpCode::JUMP_SIZE;
-                let value = self.stack.pop().unwrap();
-                if !bool::from(&value) {
+            OpCode::JumpIfTrueOrPop8 | OpCode::JumpIfTrueOrPop16 | OpCode::JumpIfTrueOrPop32 => {
+                let jump_pos = self.read_target(op_code)?;
+                self.head += op_code.size().unwrap() as usize;
+                let top = self.stack.last().ok_or(Error::StackUnderflow)?;
+                if top.is_truthy() {
                     self.head = jump_pos;
+                } else {
+                    self.stack.pop();
                 }
             }
 
             OpCode::NOP => (),
-            OpCode::LEN => todo!("{op_code:?}"),
+            OpCode::LEN => unreachable!(),
         }
+        Ok(())
+    }
+    pub fn pop(&mut self) -> Result<V, Error> {
+        self.stack.pop().ok_or(Error::StackUnderflow)
+    }
+    /// Pops the top `len` values, keeping them in push order.
+    pub fn split_top(&mut self, len: usize) -> Result<Vec<V>, Error> {
+        let at = self
+            .stack
+            .len()
+            .checked_sub(len)
+            .ok_or(Error::StackUnderflow)?;
+        Ok(self.stack.split_off(at))
+    }
+    /// The top `len` values, in push order.
+    pub fn top_mut(&mut self, len: usize) -> Result<&mut [V], Error> {
+        let at = self
+            .stack
+            .len()
+            .checked_sub(len)
+            .ok_or(Error::StackUnderflow)?;
+        Ok(&mut self.stack[at..])
     }
     #[inline]
     #[must_use]
@@ -84,9 +248,31 @@ impl<'a> Vm<'a> {
         assert!(byte < OpCode::LEN as u8);
         unsafe { std::mem::transmute(byte) }
     }
+    /// Resolves the operand of the jump at `head` against the end of the instruction.
+    pub fn read_target(&self, op_code: OpCode) -> Result<usize, Error> {
+        let end = self.head + op_code.size().unwrap() as usize;
+        instr::absolute(end, read_offset(&self.bytes, self.head, op_code))
+    }
     #[inline]
     #[must_use]
     pub fn read<const LEN: usize>(&self) -> [u8; LEN] {
         self.bytes[self.head..self.head + LEN].try_into().unwrap()
     }
 }
+
+impl<V: Slot> Machine for Vm<V> {
+    type Pool = Pool;
+
+    fn new(pool: &Pool) -> Self {
+        Vm::new(pool.as_bytes(), &pool.constants)
+    }
+    fn is_finished(&self) -> bool {
+        self.head >= self.bytes.len()
+    }
+    fn run_next(&mut self) -> Result<(), Error> {
+        Vm::run_next(self)
+    }
+    fn into_stack(self) -> Vec<Value> {
+        self.stack.into_iter().map(Slot::into_value).collect()
+    }
+}